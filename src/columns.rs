@@ -0,0 +1,371 @@
+use super::size::{format_size, SizeUnits};
+use super::users::NameTable;
+use super::utils::format_count_of;
+use std::fs::Metadata;
+use std::time::SystemTime;
+
+/// How wide the size column is, bigger sizes push the rest of the line to the right.
+const BYTES_WIDTH: usize = 10;
+const HUMAN_SIZE_WIDTH: usize = 5;
+
+/// Owner and group names are padded to this width, like `ls -l`.
+const NAME_WIDTH: usize = 8;
+
+const ISO_TIME_WIDTH: usize = 16;
+const RELATIVE_TIME_WIDTH: usize = 14;
+const EPOCH_TIME_WIDTH: usize = 10;
+
+/// The largest unit that fits is used for relative times, `3 weeks ago` rather than `21 days ago`.
+const TIME_UNITS: [(u64, &str, &str); 6] = [
+    (365 * 24 * 60 * 60, "year", "years"),
+    (30 * 24 * 60 * 60, "month", "months"),
+    (7 * 24 * 60 * 60, "week", "weeks"),
+    (24 * 60 * 60, "day", "days"),
+    (60 * 60, "hour", "hours"),
+    (60, "minute", "minutes"),
+];
+
+/// Which of the times of an entry `-t` shows.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TimeField {
+    Modified,
+    /// When the metadata last changed, or the creation time where there is no such thing.
+    Changed,
+    Accessed,
+}
+
+impl TimeField {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "mtime" | "modified" => Some(Self::Modified),
+            "ctime" | "changed" => Some(Self::Changed),
+            "atime" | "accessed" => Some(Self::Accessed),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TimeStyle {
+    /// `2024-05-01 12:34` in the local time zone.
+    Iso,
+    /// `3 days ago`.
+    Relative,
+    /// Seconds since 1970.
+    Epoch,
+}
+
+impl TimeStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "iso" => Some(Self::Iso),
+            "relative" => Some(Self::Relative),
+            "epoch" => Some(Self::Epoch),
+            _ => None,
+        }
+    }
+}
+
+/// Set with `-p`, `-u`, `-g`, `-t`, `--time` and `--time-style`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ColumnOptions {
+    pub is_mode_shown: bool,
+    pub is_owner_shown: bool,
+    pub is_group_shown: bool,
+    pub is_time_shown: bool,
+    pub time_field: TimeField,
+    pub time_style: TimeStyle,
+}
+
+impl ColumnOptions {
+    pub fn new() -> Self {
+        Self {
+            is_mode_shown: false,
+            is_owner_shown: false,
+            is_group_shown: false,
+            is_time_shown: false,
+            time_field: TimeField::Modified,
+            time_style: TimeStyle::Iso,
+        }
+    }
+}
+
+/// Formats the columns written before the branch glyphs, in the order of `tree -pugsD`:
+/// the mode, owner, group, size and time.
+///
+/// Every column has a fixed width, so the lines of the text tree stay aligned without
+/// knowing every entry up front.
+pub struct Columns {
+    options: ColumnOptions,
+    size_units: Option<SizeUnits>,
+    users: Option<NameTable>,
+    groups: Option<NameTable>,
+    // Relative times are all measured from the start of the walk.
+    now: SystemTime,
+}
+
+impl Columns {
+    pub fn new(options: ColumnOptions, size_units: Option<SizeUnits>) -> Self {
+        Self {
+            options,
+            size_units,
+            users: options
+                .is_owner_shown
+                .then(|| NameTable::read("/etc/passwd")),
+            groups: options
+                .is_group_shown
+                .then(|| NameTable::read("/etc/group")),
+            now: SystemTime::now(),
+        }
+    }
+
+    /// Whether any of the columns is read from the metadata of the entries.
+    pub fn needs_metadata(&self) -> bool {
+        self.options.is_mode_shown
+            || self.options.is_owner_shown
+            || self.options.is_group_shown
+            || self.options.is_time_shown
+    }
+
+    /// Anything that couldn't be read is shown as `?`.
+    pub fn format(&self, metadata: Option<&Metadata>, size: Option<u64>) -> String {
+        let mut columns: Vec<String> = Vec::new();
+
+        if self.options.is_mode_shown {
+            let mode = metadata.map_or("?".to_string(), get_permissions);
+            columns.push(format!("{mode:<10}"));
+        }
+
+        for (names, get_id) in [
+            (&self.users, get_uid as fn(&Metadata) -> Option<u32>),
+            (&self.groups, get_gid),
+        ] {
+            if let Some(names) = names {
+                let name = metadata
+                    .and_then(get_id)
+                    .map_or("?".to_string(), |id| names.get_name(id));
+                columns.push(format!("{name:<NAME_WIDTH$}"));
+            }
+        }
+
+        if let Some(size_units) = self.size_units {
+            let width = match size_units {
+                SizeUnits::Bytes => BYTES_WIDTH,
+                SizeUnits::Iec | SizeUnits::Si => HUMAN_SIZE_WIDTH,
+            };
+            let size = size.map_or("?".to_string(), |size| format_size(size, size_units));
+            columns.push(format!("{size:>width$}"));
+        }
+
+        if self.options.is_time_shown {
+            let time = metadata
+                .and_then(|metadata| get_time(metadata, self.options.time_field))
+                .map_or("?".to_string(), |time| self.format_time(time));
+            columns.push(match self.options.time_style {
+                TimeStyle::Iso => format!("{time:<ISO_TIME_WIDTH$}"),
+                TimeStyle::Relative => format!("{time:>RELATIVE_TIME_WIDTH$}"),
+                TimeStyle::Epoch => format!("{time:>EPOCH_TIME_WIDTH$}"),
+            });
+        }
+
+        columns.join("  ")
+    }
+
+    fn format_time(&self, time: SystemTime) -> String {
+        let local_time = chrono::DateTime::<chrono::Local>::from(time);
+
+        match self.options.time_style {
+            TimeStyle::Iso => local_time.format("%Y-%m-%d %H:%M").to_string(),
+            TimeStyle::Relative => format_relative_time(time, self.now),
+            TimeStyle::Epoch => local_time.timestamp().to_string(),
+        }
+    }
+}
+
+/// `3 days ago`, or `in 2 hours` for times in the future.
+fn format_relative_time(time: SystemTime, now: SystemTime) -> String {
+    let (seconds, is_future) = match now.duration_since(time) {
+        Ok(elapsed) => (elapsed.as_secs(), false),
+        Err(error) => (error.duration().as_secs(), true),
+    };
+
+    let Some(&(unit, singular, plural)) = TIME_UNITS.iter().find(|(unit, ..)| seconds >= *unit)
+    else {
+        return "just now".to_string();
+    };
+
+    let amount = format_count_of((seconds / unit) as usize, singular, plural);
+    if is_future {
+        format!("in {amount}")
+    } else {
+        format!("{amount} ago")
+    }
+}
+
+/// `drwxr-xr-x`, the same as `ls -l`, with `s` and `t` for the setuid, setgid and sticky bits.
+fn format_permissions(type_char: char, mode: u32) -> String {
+    let mut permissions = String::with_capacity(10);
+    permissions.push(type_char);
+
+    for (shift, special_bit, special_char) in [(6, 0o4000, 's'), (3, 0o2000, 's'), (0, 0o1000, 't')]
+    {
+        let bits = mode >> shift;
+        permissions.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        permissions.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        permissions.push(match (bits & 0o1 != 0, mode & special_bit != 0) {
+            (true, true) => special_char,
+            (false, true) => special_char.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+
+    permissions
+}
+
+#[cfg(unix)]
+fn get_permissions(metadata: &Metadata) -> String {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    let file_type = metadata.file_type();
+    let type_char = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_block_device() {
+        'b'
+    } else {
+        '-'
+    };
+
+    format_permissions(type_char, metadata.mode())
+}
+
+/// Without unix permissions, everything is readable by everyone and writable unless read-only.
+#[cfg(not(unix))]
+fn get_permissions(metadata: &Metadata) -> String {
+    let type_char = if metadata.is_dir() {
+        'd'
+    } else if metadata.is_symlink() {
+        'l'
+    } else {
+        '-'
+    };
+    let mode = if metadata.permissions().readonly() {
+        0o444
+    } else {
+        0o666
+    };
+
+    format_permissions(type_char, mode)
+}
+
+#[cfg(unix)]
+fn get_uid(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.uid())
+}
+
+#[cfg(not(unix))]
+fn get_uid(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn get_gid(metadata: &Metadata) -> Option<u32> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some(metadata.gid())
+}
+
+#[cfg(not(unix))]
+fn get_gid(_metadata: &Metadata) -> Option<u32> {
+    None
+}
+
+fn get_time(metadata: &Metadata, time_field: TimeField) -> Option<SystemTime> {
+    match time_field {
+        TimeField::Modified => metadata.modified().ok(),
+        TimeField::Accessed => metadata.accessed().ok(),
+        TimeField::Changed => get_changed_time(metadata),
+    }
+}
+
+#[cfg(unix)]
+fn get_changed_time(metadata: &Metadata) -> Option<SystemTime> {
+    use std::os::unix::fs::MetadataExt;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let seconds = u64::try_from(metadata.ctime()).ok()?;
+    let nanos = u32::try_from(metadata.ctime_nsec()).ok()?;
+    Some(UNIX_EPOCH + Duration::new(seconds, nanos))
+}
+
+#[cfg(not(unix))]
+fn get_changed_time(metadata: &Metadata) -> Option<SystemTime> {
+    metadata.created().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_format_columns() {
+        let options = ColumnOptions::new();
+        assert_eq!(Columns::new(options, None).format(None, Some(12)), "");
+        assert_eq!(
+            Columns::new(options, Some(SizeUnits::Bytes)).format(None, Some(4096)),
+            "      4096"
+        );
+        assert_eq!(
+            Columns::new(options, Some(SizeUnits::Iec)).format(None, Some(4096)),
+            " 4.0K"
+        );
+        assert_eq!(
+            Columns::new(options, Some(SizeUnits::Iec)).format(None, None),
+            "    ?"
+        );
+
+        let options = ColumnOptions {
+            is_mode_shown: true,
+            is_time_shown: true,
+            time_style: TimeStyle::Epoch,
+            ..ColumnOptions::new()
+        };
+        assert_eq!(
+            Columns::new(options, Some(SizeUnits::Iec)).format(None, None),
+            "?               ?           ?"
+        );
+    }
+
+    #[test]
+    fn test_format_permissions() {
+        assert_eq!(format_permissions('d', 0o755), "drwxr-xr-x");
+        assert_eq!(format_permissions('-', 0o640), "-rw-r-----");
+        assert_eq!(format_permissions('-', 0o4755), "-rwsr-xr-x");
+        assert_eq!(format_permissions('d', 0o1777), "drwxrwxrwt");
+        assert_eq!(format_permissions('-', 0o2644), "-rw-r-Sr--");
+    }
+
+    #[test]
+    fn test_format_relative_time() {
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        assert_eq!(format_relative_time(now, now), "just now");
+        assert_eq!(format_relative_time(now - day * 3, now), "3 days ago");
+        assert_eq!(format_relative_time(now - day * 7, now), "1 week ago");
+        assert_eq!(
+            format_relative_time(now + Duration::from_secs(2 * 60 * 60), now),
+            "in 2 hours"
+        );
+    }
+}
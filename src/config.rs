@@ -1,21 +1,153 @@
+use super::columns::{ColumnOptions, TimeField, TimeStyle};
+use super::config_error::ConfigError;
+use super::config_file::{get_default_args, DefaultArgs};
+use super::dir_hash::{DirHashMode, DirHashOptions};
+use super::filter::FilterOptions;
+use super::glob::Glob;
+use super::hash::HashAlgorithm;
+use super::output::{MarkdownStyle, OutputFormat, OutputOptions};
+use super::quote::QuoteStyle;
+use super::size::{SizeOptions, SizeUnits, SizeUsage};
+use super::sort::{DirOrder, SortKey, SortOptions};
+use super::spec::SpecKeyword;
+use std::path::{Path, PathBuf};
 use std::vec::IntoIter;
 
+/// The tags that don't take a value, the short ones can be put together like `-pug`.
+const FLAGS: [&str; 25] = [
+    "-v",
+    "--verbose",
+    "-r",
+    "--reverse",
+    "--dirs-first",
+    "--files-first",
+    "--prune",
+    "--gitignore",
+    "--check",
+    "--dir-hash",
+    "--root-hash",
+    "-s",
+    "--size",
+    "-h",
+    "--si",
+    "--disk-usage",
+    "-p",
+    "-u",
+    "-g",
+    "-t",
+    "-l",
+    "--follow",
+    "--print-errors",
+    "--total-per-root",
+    "--no-config",
+];
+
+/// Turn off a flag given by a config file or `MTREE_OPTS`, like `--no-follow`.
+pub const NEGATED_FLAGS: [&str; 8] = [
+    "--no-verbose",
+    "--no-reverse",
+    "--no-prune",
+    "--no-gitignore",
+    "--no-size",
+    "--no-follow",
+    "--no-print-errors",
+    "--no-total-per-root",
+];
+
+/// The tags followed by a value, as the next argument, after `=` like `--depth=3`, or
+/// right after a short tag like `-D3`.
+const VALUE_TAGS: [&str; 21] = [
+    "-D",
+    "--depth",
+    "-L",
+    "--dir-limit",
+    "-T",
+    "--total-limit",
+    "--sort",
+    "-I",
+    "-P",
+    "--output",
+    "--base-href",
+    "--markdown-style",
+    "--update-file",
+    "-k",
+    "-f",
+    "--hash",
+    "--dir-hash-exclude",
+    "--threshold",
+    "--time",
+    "--time-style",
+    "--quote",
+];
+
+/// The long names of the tags that started out short, they are read as the short ones.
+const LONG_ALIASES: [(&str, &str); 4] = [
+    ("--depth", "-D"),
+    ("--dir-limit", "-L"),
+    ("--total-limit", "-T"),
+    ("--verbose", "-v"),
+];
+
 #[derive(Debug, PartialEq)]
 pub struct Config {
     mode: Mode,
     is_verbose: bool,
+    is_check: bool,
+    /// The directory the base directories are relative to, empty for the current one.
+    base_dir: PathBuf,
 }
 
 #[derive(Debug, PartialEq)]
 enum Mode {
     Render {
         root_dir: String,
-        max_depth: usize,
+        /// The base directories after the first, each one is shown as its own tree.
+        extra_root_dirs: Vec<String>,
+        max_depth: Option<usize>,
         dir_len_limit: Option<usize>,
         total_len_limit: Option<usize>,
+        sort_options: SortOptions,
+        filter_options: FilterOptions,
+        output_options: OutputOptions,
+        /// Shows the digest of every file next to its name.
+        hash_algorithm: Option<HashAlgorithm>,
+        dir_hash_options: DirHashOptions,
+        size_options: SizeOptions,
+        column_options: ColumnOptions,
+        /// Lists the entries of symlinks to directories.
+        is_following_links: bool,
+        quote_style: QuoteStyle,
+        /// Writes every directory or entry that couldn't be read to standard error.
+        is_printing_errors: bool,
+        /// Applies `-T` to every base directory on its own rather than to all of them.
+        is_total_limit_per_root: bool,
+    },
+    /// `mtree spec`, writes a BSD mtree(8) specification of the directory.
+    Spec {
+        root_dir: String,
+        keywords: Vec<SpecKeyword>,
+        filter_options: FilterOptions,
+    },
+    /// `mtree verify`, compares the directory with a spec from a file or standard input.
+    Verify {
+        root_dir: String,
+        spec_file: Option<String>,
+        filter_options: FilterOptions,
     },
+    /// `mtree du`, shows the share of its parent every entry takes up.
+    Du {
+        root_dir: String,
+        max_depth: Option<usize>,
+        filter_options: FilterOptions,
+        size_options: SizeOptions,
+        /// Entries smaller than this percentage of their parent are put together.
+        threshold: f64,
+        quote_style: QuoteStyle,
+    },
+    /// Rewrites the regions between `<!-- mtree:start -->` and `<!-- mtree:end -->` in a file.
+    UpdateFile(String),
     Message(String),
-    Error(String),
+    Error(ConfigError),
 }
 
 impl Config {
@@ -23,11 +155,70 @@ impl Config {
         Self {
             mode: Mode::Render {
                 root_dir: ".".to_string(),
-                max_depth: 2,
+                extra_root_dirs: Vec::new(),
+                max_depth: Some(2),
                 dir_len_limit: None,
                 total_len_limit: None,
+                sort_options: SortOptions::new(),
+                filter_options: FilterOptions::new(),
+                output_options: OutputOptions::new(),
+                hash_algorithm: None,
+                dir_hash_options: DirHashOptions::new(),
+                size_options: SizeOptions::new(),
+                column_options: ColumnOptions::new(),
+                is_following_links: false,
+                quote_style: QuoteStyle::Replace,
+                is_printing_errors: false,
+                is_total_limit_per_root: false,
+            },
+            is_verbose: false,
+            is_check: false,
+            base_dir: PathBuf::new(),
+        }
+    }
+
+    pub fn new_spec() -> Self {
+        Self {
+            mode: Mode::Spec {
+                root_dir: ".".to_string(),
+                keywords: SpecKeyword::DEFAULT.to_vec(),
+                filter_options: FilterOptions::new(),
+            },
+            is_verbose: false,
+            is_check: false,
+            base_dir: PathBuf::new(),
+        }
+    }
+
+    pub fn new_verify() -> Self {
+        Self {
+            mode: Mode::Verify {
+                root_dir: ".".to_string(),
+                spec_file: None,
+                filter_options: FilterOptions::new(),
             },
             is_verbose: false,
+            is_check: false,
+            base_dir: PathBuf::new(),
+        }
+    }
+
+    pub fn new_du() -> Self {
+        Self {
+            mode: Mode::Du {
+                root_dir: ".".to_string(),
+                max_depth: Some(2),
+                filter_options: FilterOptions::new(),
+                size_options: SizeOptions {
+                    units: SizeUnits::Iec,
+                    ..SizeOptions::new()
+                },
+                threshold: 1.0,
+                quote_style: QuoteStyle::Replace,
+            },
+            is_verbose: false,
+            is_check: false,
+            base_dir: PathBuf::new(),
         }
     }
 
@@ -35,32 +226,72 @@ impl Config {
         Self {
             mode: Mode::Message(message),
             is_verbose: false,
+            is_check: false,
+            base_dir: PathBuf::new(),
         }
     }
 
-    pub fn new_error(error: String) -> Self {
+    pub fn new_error(error: ConfigError) -> Self {
         Self {
             mode: Mode::Error(error),
             is_verbose: false,
+            is_check: false,
+            base_dir: PathBuf::new(),
         }
     }
 
     pub fn from(args: Vec<String>) -> Self {
-        Self::parse_config(args)
+        Self::parse_config(args, Vec::new(), PathBuf::new())
+    }
+
+    /// Reads the args with the base directories relative to base_dir rather than to the
+    /// current directory, like the flags of a marker in `--update-file`.
+    pub fn from_dir(args: Vec<String>, base_dir: &Path) -> Self {
+        Self::parse_config(args, Vec::new(), base_dir.to_path_buf())
+    }
+
+    /// Reads the args on top of the defaults of the config files and `MTREE_OPTS`, see
+    /// `config_file`. Only the tree has defaults, and `--no-config` leaves them out.
+    pub fn load(args: Vec<String>) -> Self {
+        let config = Self::from(args.clone());
+
+        let is_config_off = args
+            .iter()
+            .skip(1)
+            .take_while(|arg| *arg != "--")
+            .any(|arg| arg == "--no-config");
+        if is_config_off || !matches!(config.mode, Mode::Render { .. }) {
+            return config;
+        }
+
+        let root_dir = config
+            .get_root_dir()
+            .expect("Config should be in render mode.");
+        match get_default_args(Path::new(&root_dir)) {
+            Ok(defaults) if defaults.is_empty() => config,
+            Ok(defaults) => Self::parse_config(args, defaults, PathBuf::new()),
+            Err(error) => Self::new_error(error),
+        }
     }
 
     /// Here is how the command line arguments should work:
     /// --help, --version at args[1], return Config{mode: Mode::Message(...)}
-    /// -D followed by a valid usize sets the max depth
+    /// -D followed by a valid usize sets the max depth, `inf` or `all` removes the depth limit
     /// -L followed by a valid usize sets the max length of any sub directory
     /// -T followed by a valid usize sets the total length. How many times render::render_line() is called.
     /// exactly one string not following a tag is the base directory.
+    /// `spec` at args[1] writes an mtree(8) specification instead of the tree, `-k` picks its keywords.
+    /// `verify` at args[1] checks the directory against the specification given with `-f`.
+    /// `du` at args[1] shows how much of its parent every entry takes up, down to `-D`.
+    /// `--depth=3` and `-D3` give the value in the same argument, `-vD3` puts short tags together.
+    /// `--` ends the tags, anything after it is the base directory.
+    /// The defaults are read before the args, they can only hold tags of the tree.
     /// We start with the default Config::new() and fill in values as we get them from the args.
     ///
     ///
     /// Some examples
     /// `mtree .` s
-    fn parse_config(args: Vec<String>) -> Self {
+    fn parse_config(args: Vec<String>, defaults: Vec<DefaultArgs>, base_dir: PathBuf) -> Self {
         // --help, --version at args[1], return Config{mode: Mode::Message(...)}
         if let Some(arg1) = args.get(1) {
             match &arg1[..] {
@@ -74,36 +305,139 @@ impl Config {
         args.next()
             .expect("Args should always contain at least one element.");
 
-        let mut config = Self::new();
+        // Subcommands replace the tree with something else, but share the rest of the parsing.
+        // A directory with the same name is listed when written as a path, like `./spec`.
+        let mut config = match args.as_slice().first().map(String::as_str) {
+            Some("spec") => {
+                args.next();
+                Self::new_spec()
+            }
+            Some("verify") => {
+                args.next();
+                Self::new_verify()
+            }
+            Some("du") => {
+                args.next();
+                Self::new_du()
+            }
+            _ => Self::new(),
+        };
+        config.base_dir = base_dir;
+
+        for default in defaults {
+            let result = match config.parse_args(&mut default.args.into_iter()) {
+                Ok(0) if matches!(config.mode, Mode::Render { .. }) => Ok(()),
+                Ok(_) => Err(ConfigError::Invalid(
+                    "Only the options of the tree can be given here.".to_string(),
+                )),
+                Err(error) => Err(error),
+            };
+
+            if let Err(error) = result {
+                return Config::new_error(ConfigError::InSource {
+                    source: default.source,
+                    error: Box::new(error),
+                });
+            }
+        }
+
+        if let Err(error) = config.parse_args(&mut args) {
+            return Config::new_error(error);
+        }
+
+        if config
+            .get_dir_hash_options()
+            .is_some_and(|options| options.mode == DirHashMode::Off && !options.exclude.is_empty())
+        {
+            return Config::new_error(ConfigError::Invalid(
+                "The tag `--dir-hash-exclude` needs `--dir-hash` or `--root-hash`.".to_string(),
+            ));
+        }
+
+        if config.is_check && config.get_update_file().is_none() {
+            return Config::new_error(ConfigError::Invalid(
+                "The tag `--check` needs `--update-file`.".to_string(),
+            ));
+        }
+
+        config
+    }
 
+    /// Reads tags and base directories into the config, giving how many base directories
+    /// there were.
+    fn parse_args(&mut self, args: &mut IntoIter<String>) -> Result<usize, ConfigError> {
         // -D followed by a valid usize sets the max depth
         // -L followed by a valid usize sets the max length of any sub directory
         // -T followed by a valid usize sets the total length. How many times render::render_line() is called.
         // exactly one string not following a tag is the base directory.
 
         let mut directories_contained_in_args: usize = 0;
+        // Everything after `--` is a base directory, even when it starts with `-`.
+        let mut is_reading_tags = true;
 
         while let Some(arg) = args.next() {
-            let result = match &arg[..].starts_with("-") {
-                false => {
-                    directories_contained_in_args += 1;
-                    config.set_root_dir(arg)
+            if is_reading_tags && arg == "--" {
+                is_reading_tags = false;
+            } else if !is_reading_tags || !arg.starts_with('-') || arg == "-" {
+                directories_contained_in_args += 1;
+                if directories_contained_in_args == 1 {
+                    self.set_root_dir(arg)?;
+                } else {
+                    self.add_root_dir(arg)?;
                 }
-                true => config.parse_tag_and_value(&arg[..], &mut args),
-            };
+            } else if arg.starts_with("--") {
+                self.parse_long_tag(&arg, args)?;
+            } else {
+                self.parse_short_tags(&arg, args)?;
+            }
+        }
 
-            if let Err(error) = result {
-                return Config::new_error(error);
+        Ok(directories_contained_in_args)
+    }
+
+    /// `--depth 3`, or `--depth=3` with the value in the same argument.
+    fn parse_long_tag(
+        &mut self,
+        arg: &str,
+        args: &mut IntoIter<String>,
+    ) -> Result<(), ConfigError> {
+        let Some((tag, value)) = arg.split_once('=') else {
+            return self.parse_tag_and_value(arg, args);
+        };
+
+        if FLAGS.contains(&tag) || NEGATED_FLAGS.contains(&tag) {
+            return Err(ConfigError::UnexpectedValue {
+                tag: tag.to_string(),
+            });
+        }
+
+        self.parse_tag_and_value(tag, &mut vec![value.to_string()].into_iter())
+    }
+
+    /// `-vD3` is read as `-v -D 3`: tags without a value can be put together, and
+    /// everything after a tag that takes one is its value.
+    fn parse_short_tags(
+        &mut self,
+        arg: &str,
+        args: &mut IntoIter<String>,
+    ) -> Result<(), ConfigError> {
+        let letters = &arg[1..];
+
+        for (index, letter) in letters.char_indices() {
+            let tag = format!("-{letter}");
+            if FLAGS.contains(&tag.as_str()) {
+                self.parse_tag_and_value(&tag, args)?;
+                continue;
             }
 
-            if directories_contained_in_args > 1 {
-                return Config::new_error(
-                    "The arguments can only contain up to one base directory.".to_string(),
-                );
+            let value = &letters[index + letter.len_utf8()..];
+            if value.is_empty() {
+                return self.parse_tag_and_value(&tag, args);
             }
+            return self.parse_tag_and_value(&tag, &mut vec![value.to_string()].into_iter());
         }
 
-        config
+        Ok(())
     }
 
     // I want to be able to parse `-D 10` for example and then insert 10 into the config.
@@ -113,78 +447,302 @@ impl Config {
         &mut self,
         tag: &str,
         args: &mut IntoIter<String>,
-    ) -> Result<(), String> {
+    ) -> Result<(), ConfigError> {
+        // Errors name the tag the way it was typed, but the long names are read as the short ones.
+        let typed_tag = tag;
+        let tag = LONG_ALIASES
+            .iter()
+            .find(|(long_tag, _)| *long_tag == tag)
+            .map_or(tag, |(_, short_tag)| short_tag);
+
         // Check if the tags are valid. Done twice to ensure error heirarchy.
+        if !FLAGS.contains(&tag) && !NEGATED_FLAGS.contains(&tag) && !VALUE_TAGS.contains(&tag) {
+            return Err(ConfigError::unknown_tag(tag, &get_known_tags()));
+        }
+
+        // Tags that don't take a value.
         match tag {
-            "-D" | "-L" | "-T" | "-v" => (),
-            _ => return Err(format!("The tag `{tag}` is invalid.")),
+            "-v" => {
+                self.set_is_verbose(true);
+                return Ok(());
+            }
+            "--check" => {
+                self.is_check = true;
+                return Ok(());
+            }
+            "-r" | "--reverse" => {
+                return self.update_sort_options(|options| options.reverse = true)
+            }
+            "--dirs-first" => {
+                return self.update_sort_options(|options| options.dir_order = DirOrder::DirsFirst)
+            }
+            "--files-first" => {
+                return self.update_sort_options(|options| options.dir_order = DirOrder::FilesFirst)
+            }
+            "--dir-hash" => {
+                return self.update_dir_hash_options(|options| options.mode = DirHashMode::Tree)
+            }
+            "--root-hash" => {
+                return self.update_dir_hash_options(|options| options.mode = DirHashMode::RootOnly)
+            }
+            "-s" | "--size" => return self.update_size_options(|options| options.is_shown = true),
+            "-h" => return self.update_size_options(|options| options.units = SizeUnits::Iec),
+            "--si" => return self.update_size_options(|options| options.units = SizeUnits::Si),
+            "--disk-usage" => {
+                return self.update_size_options(|options| options.usage = SizeUsage::Disk)
+            }
+            "-l" | "--follow" => return self.set_is_following_links(true),
+            "--print-errors" => return self.set_is_printing_errors(true),
+            "--total-per-root" => return self.set_is_total_limit_per_root(true),
+            // Already taken care of by `Config::load`.
+            "--no-config" => return Ok(()),
+            "-p" => return self.update_column_options(|options| options.is_mode_shown = true),
+            "-u" => return self.update_column_options(|options| options.is_owner_shown = true),
+            "-g" => return self.update_column_options(|options| options.is_group_shown = true),
+            "-t" => return self.update_column_options(|options| options.is_time_shown = true),
+            "--prune" => return self.update_filter_options(|options| options.prune = true),
+            "--gitignore" => {
+                return self.update_filter_options(|options| options.use_gitignore = true)
+            }
+            "--no-verbose" => {
+                self.set_is_verbose(false);
+                return Ok(());
+            }
+            "--no-reverse" => return self.update_sort_options(|options| options.reverse = false),
+            "--no-prune" => return self.update_filter_options(|options| options.prune = false),
+            "--no-gitignore" => {
+                return self.update_filter_options(|options| options.use_gitignore = false)
+            }
+            "--no-size" => return self.update_size_options(|options| options.is_shown = false),
+            "--no-follow" => return self.set_is_following_links(false),
+            "--no-print-errors" => return self.set_is_printing_errors(false),
+            "--no-total-per-root" => return self.set_is_total_limit_per_root(false),
+            _ => (),
         }
 
-        if tag == "-v" {
-            self.set_is_verbose(true);
-            return Ok(());
+        let value = args.next().ok_or_else(|| ConfigError::MissingValue {
+            tag: typed_tag.to_string(),
+        })?;
+
+        if tag == "--sort" {
+            let key = SortKey::from_name(&value).ok_or_else(|| invalid_value(tag, &value))?;
+            return self.update_sort_options(|options| options.key = key);
+        }
+
+        if tag == "--output" {
+            let output_format =
+                OutputFormat::from_name(&value).ok_or_else(|| invalid_value(tag, &value))?;
+            return self.update_output_options(|options| options.format = output_format);
+        }
+
+        if tag == "--markdown-style" {
+            let markdown_style =
+                MarkdownStyle::from_name(&value).ok_or_else(|| invalid_value(tag, &value))?;
+            return self.update_output_options(|options| options.markdown_style = markdown_style);
+        }
+
+        if tag == "--update-file" {
+            return self.set_update_file(value);
+        }
+
+        if tag == "--hash" {
+            let hash_algorithm =
+                HashAlgorithm::from_name(&value).ok_or_else(|| invalid_value(tag, &value))?;
+            return self.set_hash_algorithm(Some(hash_algorithm));
+        }
+
+        if tag == "-k" {
+            let keywords = SpecKeyword::parse_list(&value)?;
+            return self.set_spec_keywords(keywords);
+        }
+
+        // Picking a time or its style implies `-t`.
+        if tag == "--time" {
+            let time_field =
+                TimeField::from_name(&value).ok_or_else(|| invalid_value(tag, &value))?;
+            return self.update_column_options(|options| {
+                options.is_time_shown = true;
+                options.time_field = time_field;
+            });
+        }
+
+        if tag == "--time-style" {
+            let time_style =
+                TimeStyle::from_name(&value).ok_or_else(|| invalid_value(tag, &value))?;
+            return self.update_column_options(|options| {
+                options.is_time_shown = true;
+                options.time_style = time_style;
+            });
+        }
+
+        if tag == "--quote" {
+            let quote_style =
+                QuoteStyle::from_name(&value).ok_or_else(|| invalid_value(tag, &value))?;
+            return self.set_quote_style(quote_style);
+        }
+
+        if tag == "--threshold" {
+            let threshold = value
+                .trim_end_matches('%')
+                .parse::<f64>()
+                .ok()
+                .filter(|threshold| (0.0..=100.0).contains(threshold))
+                .ok_or_else(|| invalid_value(tag, &value))?;
+            return self.set_du_threshold(threshold);
+        }
+
+        if tag == "-f" {
+            return self.set_spec_file(value);
+        }
+
+        if tag == "--base-href" {
+            return self.update_output_options(|options| options.base_href = Some(value));
+        }
+
+        // Patterns can be given more than once, each one adds to the list.
+        if tag == "--dir-hash-exclude" {
+            let glob = Glob::new(&value)?;
+            return self.update_dir_hash_options(|options| options.exclude.push(glob));
+        }
+
+        if tag == "-I" || tag == "-P" {
+            let glob = Glob::new(&value)?;
+            return self.update_filter_options(|options| match tag {
+                "-I" => options.ignore.push(glob),
+                _ => options.only.push(glob),
+            });
         }
 
-        let value = args
-            .next()
-            .ok_or_else(|| format!("No value after tag `{tag}`."))?;
+        // The depth is the only limit that can be turned off from the command line.
+        if tag == "-D" && matches!(&value[..], "inf" | "all") {
+            return self.set_max_depth(None);
+        }
 
         let value = value
             .parse::<usize>()
-            .map_err(|_| format!("Invalid value `{value}` after tag `{tag}`"))?;
+            .map_err(|_| ConfigError::InvalidNumber {
+                tag: typed_tag.to_string(),
+                value,
+            })?;
 
         match tag {
-            "-D" => self.set_max_depth(value),
+            "-D" => self.set_max_depth(Some(value)),
             "-L" => self.set_dir_len_limit(Some(value)),
             "-T" => self.set_total_len_limit(Some(value)),
-            _ => Err(format!("The tag `{tag}` is invalid.")),
+            _ => Err(ConfigError::unknown_tag(tag, &get_known_tags())),
         }
     }
 
     pub fn get_root_dir(&self) -> Option<String> {
-        if let Mode::Render { ref root_dir, .. } = self.mode {
+        if let Mode::Render { ref root_dir, .. }
+        | Mode::Spec { ref root_dir, .. }
+        | Mode::Verify { ref root_dir, .. }
+        | Mode::Du { ref root_dir, .. } = self.mode
+        {
             Some(root_dir.clone())
         } else {
             None
         }
     }
 
-    fn set_root_dir(&mut self, new_root_dir: String) -> Result<(), String> {
-        if let Err(_error) = std::fs::read_dir(&new_root_dir) {
-            return Err(format!("The directory `{new_root_dir}` does not exist."));
-        }
+    fn set_root_dir(&mut self, new_root_dir: String) -> Result<(), ConfigError> {
+        self.check_root_dir(&new_root_dir)?;
 
         if let Mode::Render {
             ref mut root_dir, ..
+        }
+        | Mode::Spec {
+            ref mut root_dir, ..
+        }
+        | Mode::Verify {
+            ref mut root_dir, ..
+        }
+        | Mode::Du {
+            ref mut root_dir, ..
         } = self.mode
         {
             *root_dir = new_root_dir;
             Ok(())
         } else {
-            Err(
+            Err(ConfigError::Invalid(
                 "Tried to set the root directory while the Config was not in Render mode."
                     .to_string(),
-            )
+            ))
+        }
+    }
+
+    /// The base directory has to be a directory that can be listed.
+    fn check_root_dir(&self, root_dir: &str) -> Result<(), ConfigError> {
+        let path = self.base_dir.join(root_dir);
+        if std::fs::read_dir(&path).is_ok() {
+            return Ok(());
+        }
+
+        if std::fs::metadata(&path).is_ok_and(|metadata| !metadata.is_dir()) {
+            Err(ConfigError::NotADirectory(root_dir.to_string()))
+        } else {
+            Err(ConfigError::NoSuchDirectory(root_dir.to_string()))
+        }
+    }
+
+    /// Every base directory, in the order they were given.
+    pub fn get_root_dirs(&self) -> Vec<String> {
+        match self.mode {
+            Mode::Render {
+                ref root_dir,
+                ref extra_root_dirs,
+                ..
+            } => [root_dir.clone()]
+                .into_iter()
+                .chain(extra_root_dirs.iter().cloned())
+                .collect(),
+            _ => self.get_root_dir().into_iter().collect(),
+        }
+    }
+
+    /// Where the base directories are, they are shown the way they were given.
+    pub fn get_base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    /// Only the tree can show more than one base directory.
+    fn add_root_dir(&mut self, new_root_dir: String) -> Result<(), ConfigError> {
+        self.check_root_dir(&new_root_dir)?;
+
+        if let Mode::Render {
+            ref mut extra_root_dirs,
+            ..
+        } = self.mode
+        {
+            extra_root_dirs.push(new_root_dir);
+            Ok(())
+        } else {
+            Err(ConfigError::TooManyRoots)
         }
     }
 
     pub fn get_max_depth(&self) -> Option<usize> {
-        if let Mode::Render { max_depth, .. } = self.mode {
-            Some(max_depth)
+        if let Mode::Render { max_depth, .. } | Mode::Du { max_depth, .. } = self.mode {
+            max_depth
         } else {
             None
         }
     }
 
-    fn set_max_depth(&mut self, new_depth: usize) -> Result<(), String> {
+    fn set_max_depth(&mut self, new_depth: Option<usize>) -> Result<(), ConfigError> {
         if let Mode::Render {
             ref mut max_depth, ..
+        }
+        | Mode::Du {
+            ref mut max_depth, ..
         } = self.mode
         {
             *max_depth = new_depth;
             Ok(())
         } else {
-            Err("Tried to set the max depth while the Config was not in Render mode.".to_string())
+            Err(ConfigError::Invalid(
+                "Tried to set the max depth while the Config was not in Render mode.".to_string(),
+            ))
         }
     }
 
@@ -196,7 +754,7 @@ impl Config {
         }
     }
 
-    fn set_dir_len_limit(&mut self, new_dir_len_limit: Option<usize>) -> Result<(), String> {
+    fn set_dir_len_limit(&mut self, new_dir_len_limit: Option<usize>) -> Result<(), ConfigError> {
         if let Mode::Render {
             ref mut dir_len_limit,
             ..
@@ -205,7 +763,9 @@ impl Config {
             *dir_len_limit = new_dir_len_limit;
             Ok(())
         } else {
-            Err("Tried to set the max depth while the Config was not in Render mode.".to_string())
+            Err(ConfigError::Invalid(
+                "Tried to set the max depth while the Config was not in Render mode.".to_string(),
+            ))
         }
     }
 
@@ -220,7 +780,10 @@ impl Config {
         }
     }
 
-    fn set_total_len_limit(&mut self, new_total_len_limit: Option<usize>) -> Result<(), String> {
+    fn set_total_len_limit(
+        &mut self,
+        new_total_len_limit: Option<usize>,
+    ) -> Result<(), ConfigError> {
         if let Mode::Render {
             ref mut total_len_limit,
             ..
@@ -229,77 +792,513 @@ impl Config {
             *total_len_limit = new_total_len_limit;
             Ok(())
         } else {
-            Err("Tried to set the max depth while the Config was not in Render mode.".to_string())
+            Err(ConfigError::Invalid(
+                "Tried to set the max depth while the Config was not in Render mode.".to_string(),
+            ))
         }
     }
 
-    pub fn get_message(&self) -> Option<String> {
-        if let Mode::Message(ref message) = self.mode {
-            Some(message.clone())
+    pub fn get_sort_options(&self) -> Option<SortOptions> {
+        if let Mode::Render { sort_options, .. } = self.mode {
+            Some(sort_options)
         } else {
             None
         }
     }
 
-    pub fn get_error(&self) -> Option<String> {
-        if let Mode::Error(ref error) = self.mode {
-            Some(error.clone())
+    fn update_sort_options(
+        &mut self,
+        update: impl FnOnce(&mut SortOptions),
+    ) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut sort_options,
+            ..
+        } = self.mode
+        {
+            update(sort_options);
+            Ok(())
         } else {
-            None
+            Err(ConfigError::Invalid(
+                "Tried to set the sort order while the Config was not in Render mode.".to_string(),
+            ))
         }
     }
 
-    fn get_help_message() -> String {
-        std::fs::read_to_string("src/messages/help.txt")
-            .unwrap_or_else(|_| "Error displaying help message.".to_string())
+    pub fn get_filter_options(&self) -> Option<FilterOptions> {
+        if let Mode::Render {
+            ref filter_options, ..
+        }
+        | Mode::Spec {
+            ref filter_options, ..
+        }
+        | Mode::Verify {
+            ref filter_options, ..
+        }
+        | Mode::Du {
+            ref filter_options, ..
+        } = self.mode
+        {
+            Some(filter_options.clone())
+        } else {
+            None
+        }
     }
 
-    fn get_version_message() -> String {
-        std::fs::read_to_string("src/messages/version.txt")
-            .unwrap_or_else(|_| "Error displaying version message.".to_string())
+    fn update_filter_options(
+        &mut self,
+        update: impl FnOnce(&mut FilterOptions),
+    ) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut filter_options,
+            ..
+        }
+        | Mode::Spec {
+            ref mut filter_options,
+            ..
+        }
+        | Mode::Verify {
+            ref mut filter_options,
+            ..
+        }
+        | Mode::Du {
+            ref mut filter_options,
+            ..
+        } = self.mode
+        {
+            update(filter_options);
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to set a filter while the Config was not in Render mode.".to_string(),
+            ))
+        }
     }
 
-    pub fn get_is_verbose(&self) -> bool {
-        self.is_verbose
+    pub fn get_output_options(&self) -> Option<OutputOptions> {
+        if let Mode::Render {
+            ref output_options, ..
+        } = self.mode
+        {
+            Some(output_options.clone())
+        } else {
+            None
+        }
     }
 
-    fn set_is_verbose(&mut self, is_verbose: bool) {
-        self.is_verbose = is_verbose;
+    fn update_output_options(
+        &mut self,
+        update: impl FnOnce(&mut OutputOptions),
+    ) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut output_options,
+            ..
+        } = self.mode
+        {
+            update(output_options);
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to set the output format while the Config was not in Render mode."
+                    .to_string(),
+            ))
+        }
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn test_parse_args_no_arguments() {
-        let args: Vec<String> = vec!["mtree".to_string()];
-        let config = Config::from(args);
-        assert_eq!(config, Config::new());
+    pub fn get_hash_algorithm(&self) -> Option<HashAlgorithm> {
+        if let Mode::Render { hash_algorithm, .. } = self.mode {
+            hash_algorithm
+        } else {
+            None
+        }
     }
 
-    #[test]
-    fn test_parse_args_help() {
-        let args: Vec<String> = vec!["mtree".to_string(), "--help".to_string()];
-        let config = Config::from(args);
-        assert!(config.get_message().is_some());
+    fn set_hash_algorithm(
+        &mut self,
+        new_hash_algorithm: Option<HashAlgorithm>,
+    ) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut hash_algorithm,
+            ..
+        } = self.mode
+        {
+            *hash_algorithm = new_hash_algorithm;
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to set the hash while the Config was not in Render mode.".to_string(),
+            ))
+        }
     }
 
-    #[test]
-    fn test_parse_args_version() {
-        let args: Vec<String> = vec!["mtree".to_string(), "--version".to_string()];
-        let config = Config::from(args);
-        assert!(config.get_message().is_some());
+    pub fn get_dir_hash_options(&self) -> Option<DirHashOptions> {
+        if let Mode::Render {
+            ref dir_hash_options,
+            ..
+        } = self.mode
+        {
+            Some(dir_hash_options.clone())
+        } else {
+            None
+        }
     }
 
-    #[test]
-    fn test_parse_args_with_valid_base_dir() {
-        let args: Vec<String> = vec!["mtree".to_string(), "C:/Windows".to_string()];
-        let config = Config::from(args);
-        assert_eq!(config.get_root_dir(), Some("C:/Windows".to_string()));
-        assert_eq!(config.get_max_depth(), Some(2));
-    }
+    fn update_dir_hash_options(
+        &mut self,
+        update: impl FnOnce(&mut DirHashOptions),
+    ) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut dir_hash_options,
+            ..
+        } = self.mode
+        {
+            update(dir_hash_options);
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to set the directory hash while the Config was not in Render mode."
+                    .to_string(),
+            ))
+        }
+    }
+
+    pub fn get_size_options(&self) -> Option<SizeOptions> {
+        if let Mode::Render { size_options, .. } | Mode::Du { size_options, .. } = self.mode {
+            Some(size_options)
+        } else {
+            None
+        }
+    }
+
+    fn update_size_options(
+        &mut self,
+        update: impl FnOnce(&mut SizeOptions),
+    ) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut size_options,
+            ..
+        }
+        | Mode::Du {
+            ref mut size_options,
+            ..
+        } = self.mode
+        {
+            update(size_options);
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to set the sizes while the Config was not in Render mode.".to_string(),
+            ))
+        }
+    }
+
+    pub fn get_column_options(&self) -> Option<ColumnOptions> {
+        if let Mode::Render { column_options, .. } = self.mode {
+            Some(column_options)
+        } else {
+            None
+        }
+    }
+
+    fn update_column_options(
+        &mut self,
+        update: impl FnOnce(&mut ColumnOptions),
+    ) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut column_options,
+            ..
+        } = self.mode
+        {
+            update(column_options);
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to set the columns while the Config was not in Render mode.".to_string(),
+            ))
+        }
+    }
+
+    pub fn get_is_following_links(&self) -> bool {
+        matches!(
+            self.mode,
+            Mode::Render {
+                is_following_links: true,
+                ..
+            }
+        )
+    }
+
+    fn set_is_following_links(&mut self, new_is_following_links: bool) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut is_following_links,
+            ..
+        } = self.mode
+        {
+            *is_following_links = new_is_following_links;
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to follow links while the Config was not in Render mode.".to_string(),
+            ))
+        }
+    }
+
+    pub fn get_is_printing_errors(&self) -> bool {
+        matches!(
+            self.mode,
+            Mode::Render {
+                is_printing_errors: true,
+                ..
+            }
+        )
+    }
+
+    fn set_is_printing_errors(&mut self, new_is_printing_errors: bool) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut is_printing_errors,
+            ..
+        } = self.mode
+        {
+            *is_printing_errors = new_is_printing_errors;
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to print errors while the Config was not in Render mode.".to_string(),
+            ))
+        }
+    }
+
+    pub fn get_is_total_limit_per_root(&self) -> bool {
+        matches!(
+            self.mode,
+            Mode::Render {
+                is_total_limit_per_root: true,
+                ..
+            }
+        )
+    }
+
+    fn set_is_total_limit_per_root(
+        &mut self,
+        new_is_total_limit_per_root: bool,
+    ) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut is_total_limit_per_root,
+            ..
+        } = self.mode
+        {
+            *is_total_limit_per_root = new_is_total_limit_per_root;
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to set the total limit while the Config was not in Render mode.".to_string(),
+            ))
+        }
+    }
+
+    pub fn get_quote_style(&self) -> Option<QuoteStyle> {
+        if let Mode::Render { quote_style, .. } | Mode::Du { quote_style, .. } = self.mode {
+            Some(quote_style)
+        } else {
+            None
+        }
+    }
+
+    fn set_quote_style(&mut self, new_quote_style: QuoteStyle) -> Result<(), ConfigError> {
+        if let Mode::Render {
+            ref mut quote_style,
+            ..
+        }
+        | Mode::Du {
+            ref mut quote_style,
+            ..
+        } = self.mode
+        {
+            *quote_style = new_quote_style;
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to set the quoting while the Config was not in Render mode.".to_string(),
+            ))
+        }
+    }
+
+    pub fn get_du_threshold(&self) -> Option<f64> {
+        if let Mode::Du { threshold, .. } = self.mode {
+            Some(threshold)
+        } else {
+            None
+        }
+    }
+
+    fn set_du_threshold(&mut self, new_threshold: f64) -> Result<(), ConfigError> {
+        if let Mode::Du {
+            ref mut threshold, ..
+        } = self.mode
+        {
+            *threshold = new_threshold;
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "The tag `--threshold` can only be used with `mtree du`.".to_string(),
+            ))
+        }
+    }
+
+    pub fn get_spec_keywords(&self) -> Option<Vec<SpecKeyword>> {
+        if let Mode::Spec { ref keywords, .. } = self.mode {
+            Some(keywords.clone())
+        } else {
+            None
+        }
+    }
+
+    fn set_spec_keywords(&mut self, new_keywords: Vec<SpecKeyword>) -> Result<(), ConfigError> {
+        if let Mode::Spec {
+            ref mut keywords, ..
+        } = self.mode
+        {
+            *keywords = new_keywords;
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "The tag `-k` can only be used with `mtree spec`.".to_string(),
+            ))
+        }
+    }
+
+    pub fn get_spec_file(&self) -> Option<String> {
+        if let Mode::Verify { ref spec_file, .. } = self.mode {
+            spec_file.clone()
+        } else {
+            None
+        }
+    }
+
+    pub fn get_is_verify(&self) -> bool {
+        matches!(self.mode, Mode::Verify { .. })
+    }
+
+    fn set_spec_file(&mut self, new_spec_file: String) -> Result<(), ConfigError> {
+        if let Mode::Verify {
+            ref mut spec_file, ..
+        } = self.mode
+        {
+            *spec_file = Some(new_spec_file);
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "The tag `-f` can only be used with `mtree verify`.".to_string(),
+            ))
+        }
+    }
+
+    pub fn get_update_file(&self) -> Option<String> {
+        if let Mode::UpdateFile(ref file) = self.mode {
+            Some(file.clone())
+        } else {
+            None
+        }
+    }
+
+    fn set_update_file(&mut self, file: String) -> Result<(), ConfigError> {
+        if let Mode::Render { .. } = self.mode {
+            self.mode = Mode::UpdateFile(file);
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(
+                "Tried to set the file to update while the Config was not in Render mode."
+                    .to_string(),
+            ))
+        }
+    }
+
+    pub fn get_is_check(&self) -> bool {
+        self.is_check
+    }
+
+    pub fn get_message(&self) -> Option<String> {
+        if let Mode::Message(ref message) = self.mode {
+            Some(message.clone())
+        } else {
+            None
+        }
+    }
+
+    pub fn get_error(&self) -> Option<ConfigError> {
+        if let Mode::Error(ref error) = self.mode {
+            Some(error.clone())
+        } else {
+            None
+        }
+    }
+
+    fn get_help_message() -> String {
+        std::fs::read_to_string("src/messages/help.txt")
+            .unwrap_or_else(|_| "Error displaying help message.".to_string())
+    }
+
+    fn get_version_message() -> String {
+        std::fs::read_to_string("src/messages/version.txt")
+            .unwrap_or_else(|_| "Error displaying version message.".to_string())
+    }
+
+    pub fn get_is_verbose(&self) -> bool {
+        self.is_verbose
+    }
+
+    fn set_is_verbose(&mut self, is_verbose: bool) {
+        self.is_verbose = is_verbose;
+    }
+}
+
+fn get_known_tags() -> Vec<&'static str> {
+    [
+        FLAGS.as_slice(),
+        NEGATED_FLAGS.as_slice(),
+        VALUE_TAGS.as_slice(),
+    ]
+    .concat()
+}
+
+fn invalid_value(tag: &str, value: &str) -> ConfigError {
+    ConfigError::InvalidValue {
+        tag: tag.to_string(),
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_args_no_arguments() {
+        let args: Vec<String> = vec!["mtree".to_string()];
+        let config = Config::from(args);
+        assert_eq!(config, Config::new());
+    }
+
+    #[test]
+    fn test_parse_args_help() {
+        let args: Vec<String> = vec!["mtree".to_string(), "--help".to_string()];
+        let config = Config::from(args);
+        assert!(config.get_message().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_version() {
+        let args: Vec<String> = vec!["mtree".to_string(), "--version".to_string()];
+        let config = Config::from(args);
+        assert!(config.get_message().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_with_valid_base_dir() {
+        let args: Vec<String> = vec!["mtree".to_string(), "C:/Windows".to_string()];
+        let config = Config::from(args);
+        assert_eq!(config.get_root_dir(), Some("C:/Windows".to_string()));
+        assert_eq!(config.get_max_depth(), Some(2));
+    }
 
     #[test]
     fn test_parse_args_with_invalid_base_dir() {
@@ -308,6 +1307,209 @@ mod tests {
         assert!(config.get_error().is_some());
     }
 
+    #[test]
+    fn test_parse_args_forms() {
+        // Every pair of arguments should give the same config.
+        let cases: [(&[&str], &[&str]); 12] = [
+            (&["--depth", "3"], &["-D", "3"]),
+            (&["--depth=3"], &["-D", "3"]),
+            (&["-D3"], &["-D", "3"]),
+            (&["-Dinf"], &["-D", "inf"]),
+            (&["--dir-limit=10"], &["-L", "10"]),
+            (&["--total-limit", "30"], &["-T", "30"]),
+            (&["--verbose"], &["-v"]),
+            (&["-vD3"], &["-v", "-D", "3"]),
+            (&["-vD", "3"], &["-v", "-D", "3"]),
+            (&["-pugs"], &["-p", "-u", "-g", "-s"]),
+            (
+                &["--sort=size", "-rI*.rs"],
+                &["--sort", "size", "-r", "-I", "*.rs"],
+            ),
+            (&["-v", "--", "."], &["-v", "."]),
+        ];
+
+        for (args, expected_args) in cases {
+            let get_config = |args: &[&str]| {
+                let mut all_args = vec!["mtree".to_string()];
+                all_args.extend(args.iter().map(|arg| arg.to_string()));
+                Config::from(all_args)
+            };
+
+            let config = get_config(args);
+            assert!(config.get_error().is_none(), "{args:?}");
+            assert_eq!(config, get_config(expected_args), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_args_multiple_roots() {
+        assert_eq!(Config::new().get_root_dirs(), vec![".".to_string()]);
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "src".to_string(),
+            "-T".to_string(),
+            "30".to_string(),
+            ".".to_string(),
+            "--total-per-root".to_string(),
+        ];
+        let config = Config::from(args);
+        assert_eq!(config.get_root_dir(), Some("src".to_string()));
+        assert_eq!(
+            config.get_root_dirs(),
+            vec!["src".to_string(), ".".to_string()]
+        );
+        assert!(config.get_is_total_limit_per_root());
+    }
+
+    #[test]
+    fn test_parse_args_with_defaults() {
+        let get_defaults = |sources: &[(&str, &[&str])]| {
+            sources
+                .iter()
+                .map(|(source, args)| DefaultArgs {
+                    source: source.to_string(),
+                    args: args.iter().map(|arg| arg.to_string()).collect(),
+                })
+                .collect::<Vec<DefaultArgs>>()
+        };
+        let args = |args: &[&str]| -> Vec<String> {
+            ["mtree"]
+                .iter()
+                .chain(args)
+                .map(|arg| arg.to_string())
+                .collect()
+        };
+
+        // The command line wins over the defaults, and later defaults over earlier ones.
+        let defaults = get_defaults(&[
+            ("config.toml", &["--depth=4", "--dir-limit=20", "-Itarget"]),
+            ("MTREE_OPTS", &["-D", "5"]),
+        ]);
+        let config =
+            Config::parse_config(args(&["-L", "3", "-I", "*.lock"]), defaults, PathBuf::new());
+        assert_eq!(config.get_max_depth(), Some(5));
+        assert_eq!(config.get_dir_len_limit(), Some(3));
+        assert_eq!(config.get_filter_options().unwrap().ignore.len(), 2);
+
+        // Flags given by the defaults can be turned off again.
+        let defaults = get_defaults(&[("config.toml", &["--follow", "--verbose"])]);
+        let config = Config::parse_config(args(&["--no-follow"]), defaults, PathBuf::new());
+        assert!(!config.get_is_following_links());
+        assert!(config.get_is_verbose());
+        assert_eq!(
+            Config::from(args(&["--no-size=1"])).get_error(),
+            Some(ConfigError::UnexpectedValue {
+                tag: "--no-size".to_string()
+            })
+        );
+
+        for default_args in [&["."][..], &["--update-file", "README.md"], &["--bogus"]] {
+            let defaults = get_defaults(&[("MTREE_OPTS", default_args)]);
+            let error = Config::parse_config(args(&[]), defaults, PathBuf::new()).get_error();
+            assert!(
+                matches!(error, Some(ConfigError::InSource { ref source, .. }) if source == "MTREE_OPTS"),
+                "{default_args:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_args_errors() {
+        let get_error = |args: &[&str]| {
+            let mut all_args = vec!["mtree".to_string()];
+            all_args.extend(args.iter().map(|arg| arg.to_string()));
+            Config::from(all_args).get_error()
+        };
+
+        assert_eq!(
+            get_error(&["-d", "3"]),
+            Some(ConfigError::UnknownTag {
+                tag: "-d".to_string(),
+                suggestion: Some("-D"),
+            })
+        );
+        assert_eq!(
+            get_error(&["--folow"]),
+            Some(ConfigError::UnknownTag {
+                tag: "--folow".to_string(),
+                suggestion: Some("--follow"),
+            })
+        );
+        assert_eq!(
+            get_error(&["-T"]),
+            Some(ConfigError::MissingValue {
+                tag: "-T".to_string()
+            })
+        );
+        assert_eq!(
+            get_error(&["-L", "ten"]),
+            Some(ConfigError::InvalidNumber {
+                tag: "-L".to_string(),
+                value: "ten".to_string(),
+            })
+        );
+        assert_eq!(
+            get_error(&["--sort", "colour"]),
+            Some(ConfigError::InvalidValue {
+                tag: "--sort".to_string(),
+                value: "colour".to_string(),
+            })
+        );
+        assert_eq!(
+            get_error(&["C:/awoooo0ooogaaaa"]),
+            Some(ConfigError::NoSuchDirectory(
+                "C:/awoooo0ooogaaaa".to_string()
+            ))
+        );
+        assert_eq!(
+            get_error(&["Cargo.toml"]),
+            Some(ConfigError::NotADirectory("Cargo.toml".to_string()))
+        );
+        assert_eq!(
+            get_error(&["du", ".", "."]),
+            Some(ConfigError::TooManyRoots)
+        );
+
+        let cases: [(&[&str], ConfigError); 5] = [
+            (
+                &["--follow=yes"],
+                ConfigError::UnexpectedValue {
+                    tag: "--follow".to_string(),
+                },
+            ),
+            (
+                &["--depth"],
+                ConfigError::MissingValue {
+                    tag: "--depth".to_string(),
+                },
+            ),
+            (
+                &["--depth=deep"],
+                ConfigError::InvalidNumber {
+                    tag: "--depth".to_string(),
+                    value: "deep".to_string(),
+                },
+            ),
+            (
+                &["-vx"],
+                ConfigError::UnknownTag {
+                    tag: "-x".to_string(),
+                    suggestion: None,
+                },
+            ),
+            // After `--`, even `-v` is a base directory.
+            (
+                &["--", "-v"],
+                ConfigError::NoSuchDirectory("-v".to_string()),
+            ),
+        ];
+
+        for (args, expected_error) in cases {
+            assert_eq!(get_error(args), Some(expected_error), "{args:?}");
+        }
+    }
+
     #[test]
     fn test_parse_args_with_max_depth() {
         let args: Vec<String> = vec![
@@ -321,6 +1523,23 @@ mod tests {
         assert_eq!(config.get_root_dir(), Some("C:/Windows".to_string()));
     }
 
+    #[test]
+    fn test_parse_args_with_unlimited_depth() {
+        for value in ["inf", "all"] {
+            let args: Vec<String> = vec!["mtree".to_string(), "-D".to_string(), value.to_string()];
+            let config = Config::from(args);
+            assert!(config.get_error().is_none());
+            assert_eq!(config.get_max_depth(), None);
+        }
+    }
+
+    #[test]
+    fn test_parse_args_with_zero_depth() {
+        let args: Vec<String> = vec!["mtree".to_string(), "-D".to_string(), "0".to_string()];
+        let config = Config::from(args);
+        assert_eq!(config.get_max_depth(), Some(0));
+    }
+
     #[test]
     fn test_parse_args_with_dir_len_limit() {
         let args: Vec<String> = vec![
@@ -410,6 +1629,455 @@ mod tests {
         assert!(config.get_error().is_some());
     }
 
+    #[test]
+    fn test_parse_args_default_sort() {
+        let args: Vec<String> = vec!["mtree".to_string()];
+        let config = Config::from(args);
+        assert_eq!(config.get_sort_options(), Some(SortOptions::new()));
+        assert_eq!(config.get_sort_options().unwrap().key, SortKey::Name);
+    }
+
+    #[test]
+    fn test_parse_args_with_sort_options() {
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--sort".to_string(),
+            "size".to_string(),
+            "-r".to_string(),
+            "--dirs-first".to_string(),
+        ];
+        let config = Config::from(args);
+        assert_eq!(
+            config.get_sort_options(),
+            Some(SortOptions {
+                key: SortKey::Size,
+                reverse: true,
+                dir_order: DirOrder::DirsFirst,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_args_invalid_sort_key() {
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--sort".to_string(),
+            "colour".to_string(),
+        ];
+        let config = Config::from(args);
+        assert!(config.get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_with_filters() {
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "-I".to_string(),
+            "target".to_string(),
+            "-I".to_string(),
+            "*.o".to_string(),
+            "-P".to_string(),
+            "*.rs".to_string(),
+            "--prune".to_string(),
+        ];
+        let config = Config::from(args);
+        let filter_options = config.get_filter_options().unwrap();
+        assert_eq!(filter_options.ignore.len(), 2);
+        assert_eq!(filter_options.only, vec![Glob::new("*.rs").unwrap()]);
+        assert!(filter_options.prune);
+        assert!(!filter_options.use_gitignore);
+        assert!(!filter_options.is_visible("target", "target", true));
+        assert!(!filter_options.is_visible("src/main.o", "main.o", false));
+        assert!(filter_options.is_visible("src", "src", true));
+        assert!(filter_options.is_visible("src/main.rs", "main.rs", false));
+        assert!(!filter_options.is_visible("Cargo.toml", "Cargo.toml", false));
+    }
+
+    #[test]
+    fn test_parse_args_with_gitignore() {
+        let args: Vec<String> = vec!["mtree".to_string(), "--gitignore".to_string()];
+        let config = Config::from(args);
+        assert!(config.get_filter_options().unwrap().use_gitignore);
+    }
+
+    #[test]
+    fn test_parse_args_invalid_pattern() {
+        let args: Vec<String> = vec!["mtree".to_string(), "-I".to_string(), "[abc".to_string()];
+        let config = Config::from(args);
+        assert!(config.get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_with_output_format() {
+        let args: Vec<String> = vec!["mtree".to_string()];
+        let config = Config::from(args);
+        assert_eq!(
+            config.get_output_options().unwrap().format,
+            OutputFormat::Text
+        );
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--output".to_string(),
+            "json".to_string(),
+        ];
+        let config = Config::from(args);
+        assert_eq!(
+            config.get_output_options().unwrap().format,
+            OutputFormat::Json
+        );
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--output".to_string(),
+            "yaml".to_string(),
+        ];
+        let config = Config::from(args);
+        assert!(config.get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_with_base_href() {
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--output".to_string(),
+            "html".to_string(),
+            "--base-href".to_string(),
+            "https://example.com/repo".to_string(),
+        ];
+        let config = Config::from(args);
+        let output_options = config.get_output_options().unwrap();
+        assert_eq!(output_options.format, OutputFormat::Html);
+        assert_eq!(
+            output_options.base_href,
+            Some("https://example.com/repo".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_args_with_markdown_style() {
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--output".to_string(),
+            "markdown".to_string(),
+            "--markdown-style".to_string(),
+            "list".to_string(),
+        ];
+        let config = Config::from(args);
+        let output_options = config.get_output_options().unwrap();
+        assert_eq!(output_options.format, OutputFormat::Markdown);
+        assert_eq!(output_options.markdown_style, MarkdownStyle::List);
+    }
+
+    #[test]
+    fn test_parse_args_with_update_file() {
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--update-file".to_string(),
+            "README.md".to_string(),
+            "--check".to_string(),
+        ];
+        let config = Config::from(args);
+        assert_eq!(config.get_update_file(), Some("README.md".to_string()));
+        assert!(config.get_is_check());
+        assert_eq!(config.get_root_dir(), None);
+    }
+
+    #[test]
+    fn test_parse_args_check_without_update_file() {
+        let args: Vec<String> = vec!["mtree".to_string(), "--check".to_string()];
+        let config = Config::from(args);
+        assert!(config.get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_with_hash() {
+        assert_eq!(Config::new().get_hash_algorithm(), None);
+
+        for (name, hash_algorithm) in [
+            ("sha256", HashAlgorithm::Sha256),
+            ("blake3", HashAlgorithm::Blake3),
+            ("md5", HashAlgorithm::Md5),
+        ] {
+            let args: Vec<String> =
+                vec!["mtree".to_string(), "--hash".to_string(), name.to_string()];
+            let config = Config::from(args);
+            assert_eq!(config.get_hash_algorithm(), Some(hash_algorithm));
+        }
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--hash".to_string(),
+            "crc32".to_string(),
+        ];
+        assert!(Config::from(args).get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_with_dir_hash() {
+        let config = Config::new();
+        assert_eq!(config.get_dir_hash_options(), Some(DirHashOptions::new()));
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--dir-hash".to_string(),
+            "--dir-hash-exclude".to_string(),
+            "target".to_string(),
+        ];
+        let options = Config::from(args).get_dir_hash_options().unwrap();
+        assert_eq!(options.mode, DirHashMode::Tree);
+        assert_eq!(options.exclude.len(), 1);
+
+        let args: Vec<String> = vec!["mtree".to_string(), "--root-hash".to_string()];
+        let options = Config::from(args).get_dir_hash_options().unwrap();
+        assert_eq!(options.mode, DirHashMode::RootOnly);
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--dir-hash-exclude".to_string(),
+            "target".to_string(),
+        ];
+        assert!(Config::from(args).get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_with_size() {
+        assert_eq!(Config::new().get_size_options(), Some(SizeOptions::new()));
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "-s".to_string(),
+            "-h".to_string(),
+            "--disk-usage".to_string(),
+            "--sort".to_string(),
+            "total".to_string(),
+        ];
+        let config = Config::from(args);
+        let options = config.get_size_options().unwrap();
+        assert!(options.is_shown);
+        assert_eq!(options.units, SizeUnits::Iec);
+        assert_eq!(options.usage, SizeUsage::Disk);
+        assert_eq!(config.get_sort_options().unwrap().key, SortKey::Total);
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--size".to_string(),
+            "--si".to_string(),
+        ];
+        let options = Config::from(args).get_size_options().unwrap();
+        assert!(options.is_shown);
+        assert_eq!(options.units, SizeUnits::Si);
+    }
+
+    #[test]
+    fn test_parse_args_spec() {
+        let args: Vec<String> = vec!["mtree".to_string(), "spec".to_string()];
+        let config = Config::from(args);
+        assert_eq!(config, Config::new_spec());
+        assert_eq!(config.get_root_dir(), Some(".".to_string()));
+        assert_eq!(
+            config.get_spec_keywords(),
+            Some(SpecKeyword::DEFAULT.to_vec())
+        );
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "spec".to_string(),
+            "-k".to_string(),
+            "type,size".to_string(),
+            "-I".to_string(),
+            "*.o".to_string(),
+            "C:/Windows".to_string(),
+        ];
+        let config = Config::from(args);
+        assert_eq!(config.get_root_dir(), Some("C:/Windows".to_string()));
+        assert_eq!(
+            config.get_spec_keywords(),
+            Some(vec![SpecKeyword::Type, SpecKeyword::Size])
+        );
+        assert_eq!(config.get_filter_options().unwrap().ignore.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_args_directory_named_like_subcommand() {
+        let base_dir =
+            std::env::temp_dir().join(format!("mtree-config-test-{}", std::process::id()));
+        std::fs::create_dir_all(base_dir.join("spec")).unwrap();
+
+        let get_config =
+            |arg: &str| Config::from_dir(vec!["mtree".to_string(), arg.to_string()], &base_dir);
+        let subcommand_config = get_config("spec");
+        let directory_config = get_config("./spec");
+        std::fs::remove_dir_all(&base_dir).unwrap();
+
+        // The subcommand wins, a path to the directory lists it instead.
+        assert!(subcommand_config.get_spec_keywords().is_some());
+        assert!(directory_config.get_spec_keywords().is_none());
+        assert_eq!(directory_config.get_root_dir(), Some("./spec".to_string()));
+    }
+
+    #[test]
+    fn test_parse_args_spec_invalid() {
+        for args in [
+            vec!["spec", "-k", "flags"],
+            vec!["-k", "type"],
+            vec!["spec", "-D", "2"],
+        ] {
+            let mut args: Vec<String> = args.into_iter().map(String::from).collect();
+            args.insert(0, "mtree".to_string());
+            let config = Config::from(args);
+            assert!(config.get_error().is_some());
+        }
+    }
+
+    #[test]
+    fn test_parse_args_verify() {
+        let args: Vec<String> = vec!["mtree".to_string(), "verify".to_string()];
+        let config = Config::from(args);
+        assert_eq!(config, Config::new_verify());
+        assert!(config.get_is_verify());
+        assert_eq!(config.get_spec_file(), None);
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "verify".to_string(),
+            "-f".to_string(),
+            "manifest.spec".to_string(),
+            "C:/Windows".to_string(),
+        ];
+        let config = Config::from(args);
+        assert_eq!(config.get_spec_file(), Some("manifest.spec".to_string()));
+        assert_eq!(config.get_root_dir(), Some("C:/Windows".to_string()));
+
+        let args: Vec<String> = vec!["mtree".to_string(), "-f".to_string(), "a".to_string()];
+        assert!(Config::from(args).get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_with_columns() {
+        assert_eq!(
+            Config::new().get_column_options(),
+            Some(ColumnOptions::new())
+        );
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "-p".to_string(),
+            "-u".to_string(),
+            "-g".to_string(),
+            "-t".to_string(),
+        ];
+        let options = Config::from(args).get_column_options().unwrap();
+        assert!(options.is_mode_shown && options.is_owner_shown && options.is_group_shown);
+        assert!(options.is_time_shown);
+        assert_eq!(options.time_field, TimeField::Modified);
+        assert_eq!(options.time_style, TimeStyle::Iso);
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--time".to_string(),
+            "ctime".to_string(),
+            "--time-style".to_string(),
+            "relative".to_string(),
+        ];
+        let options = Config::from(args).get_column_options().unwrap();
+        assert!(options.is_time_shown);
+        assert_eq!(options.time_field, TimeField::Changed);
+        assert_eq!(options.time_style, TimeStyle::Relative);
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "--time-style".to_string(),
+            "locale".to_string(),
+        ];
+        assert!(Config::from(args).get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_follow() {
+        assert!(!Config::new().get_is_following_links());
+
+        for tag in ["-l", "--follow"] {
+            let args: Vec<String> = vec!["mtree".to_string(), tag.to_string()];
+            assert!(Config::from(args).get_is_following_links());
+        }
+
+        let args: Vec<String> = vec!["mtree".to_string(), "spec".to_string(), "-l".to_string()];
+        assert!(Config::from(args).get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_print_errors() {
+        assert!(!Config::new().get_is_printing_errors());
+
+        let args: Vec<String> = vec!["mtree".to_string(), "--print-errors".to_string()];
+        assert!(Config::from(args).get_is_printing_errors());
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "du".to_string(),
+            "--print-errors".to_string(),
+        ];
+        assert!(Config::from(args).get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_quote() {
+        assert_eq!(Config::new().get_quote_style(), Some(QuoteStyle::Replace));
+
+        let args: Vec<String> = vec!["mtree".to_string(), "--quote=shell".to_string()];
+        assert_eq!(
+            Config::from(args).get_quote_style(),
+            Some(QuoteStyle::Shell)
+        );
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "du".to_string(),
+            "--quote".to_string(),
+            "c".to_string(),
+        ];
+        assert_eq!(Config::from(args).get_quote_style(), Some(QuoteStyle::C));
+
+        let args: Vec<String> = vec!["mtree".to_string(), "--quote=none".to_string()];
+        assert!(Config::from(args).get_error().is_some());
+    }
+
+    #[test]
+    fn test_parse_args_du() {
+        let args: Vec<String> = vec!["mtree".to_string(), "du".to_string()];
+        let config = Config::from(args);
+        assert_eq!(config, Config::new_du());
+        assert_eq!(config.get_du_threshold(), Some(1.0));
+        assert_eq!(config.get_size_options().unwrap().units, SizeUnits::Iec);
+
+        let args: Vec<String> = vec![
+            "mtree".to_string(),
+            "du".to_string(),
+            "-D".to_string(),
+            "4".to_string(),
+            "--threshold".to_string(),
+            "2.5%".to_string(),
+            "--si".to_string(),
+            "C:/Windows".to_string(),
+        ];
+        let config = Config::from(args);
+        assert_eq!(config.get_max_depth(), Some(4));
+        assert_eq!(config.get_du_threshold(), Some(2.5));
+        assert_eq!(config.get_size_options().unwrap().units, SizeUnits::Si);
+        assert_eq!(config.get_root_dir(), Some("C:/Windows".to_string()));
+
+        for args in [
+            vec!["du", "--threshold", "120"],
+            vec!["--threshold", "1"],
+            vec!["du", "--sort", "size"],
+        ] {
+            let mut args: Vec<String> = args.into_iter().map(String::from).collect();
+            args.insert(0, "mtree".to_string());
+            assert!(Config::from(args).get_error().is_some());
+        }
+    }
+
     #[test]
     fn test_parse_args_is_verbose() {
         let args: Vec<String> = vec!["mtree".to_string(), "-v".to_string()];
@@ -0,0 +1,182 @@
+//! Why the command line couldn't be read. These are written to standard error together
+//! with a hint, and make mtree exit with a usage error.
+use std::fmt;
+
+/// Typos further from every tag than this aren't given a suggestion.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ConfigError {
+    /// The tag doesn't exist, with the closest one that does when there is one.
+    UnknownTag {
+        tag: String,
+        suggestion: Option<&'static str>,
+    },
+    /// A tag that doesn't take a value was given one, like `--follow=yes`.
+    UnexpectedValue {
+        tag: String,
+    },
+    /// The tag was the last argument.
+    MissingValue {
+        tag: String,
+    },
+    /// The value isn't one of the names the tag accepts.
+    InvalidValue {
+        tag: String,
+        value: String,
+    },
+    /// The tag takes a number and the value isn't one.
+    InvalidNumber {
+        tag: String,
+        value: String,
+    },
+    NoSuchDirectory(String),
+    /// The base directory is a file or something else that can't be listed.
+    NotADirectory(String),
+    TooManyRoots,
+    /// Anything else, like a tag used with a subcommand it has no meaning for.
+    Invalid(String),
+    /// The error is in a config file or `MTREE_OPTS`, rather than on the command line.
+    InSource {
+        source: String,
+        error: Box<ConfigError>,
+    },
+}
+
+impl ConfigError {
+    /// Suggests the tag among the known ones that is closest to the unknown tag.
+    pub fn unknown_tag(tag: &str, known_tags: &[&'static str]) -> Self {
+        Self::UnknownTag {
+            tag: tag.to_string(),
+            suggestion: suggest_tag(tag, known_tags),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownTag {
+                tag,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "The tag `{tag}` is invalid, did you mean `{suggestion}`?"
+            ),
+            Self::UnknownTag {
+                tag,
+                suggestion: None,
+            } => write!(f, "The tag `{tag}` is invalid."),
+            Self::UnexpectedValue { tag } => write!(f, "The tag `{tag}` doesn't take a value."),
+            Self::MissingValue { tag } => write!(f, "No value after tag `{tag}`."),
+            Self::InvalidValue { tag, value } => {
+                write!(f, "Invalid value `{value}` after tag `{tag}`.")
+            }
+            Self::InvalidNumber { tag, value } => {
+                write!(
+                    f,
+                    "Invalid value `{value}` after tag `{tag}`, it should be a number."
+                )
+            }
+            Self::NoSuchDirectory(root_dir) => {
+                write!(f, "The directory `{root_dir}` does not exist.")
+            }
+            Self::NotADirectory(root_dir) => {
+                write!(f, "`{root_dir}` is not a directory.")
+            }
+            Self::TooManyRoots => {
+                write!(f, "Only the tree can show more than one base directory.")
+            }
+            Self::Invalid(message) => write!(f, "{message}"),
+            Self::InSource { source, error } => write!(f, "In `{source}`: {error}"),
+        }
+    }
+}
+
+/// The errors of patterns and spec keywords are already written for people.
+impl From<String> for ConfigError {
+    fn from(message: String) -> Self {
+        Self::Invalid(message)
+    }
+}
+
+/// The known tag closest to tag, when it is close enough to be a typo of it.
+///
+/// A tag that only differs in case, like `-d` for `-D`, wins over every other one. Short
+/// tags are one letter away from most of the others, so the distance can be at most a
+/// third of the length of the tag for anything to be suggested.
+fn suggest_tag(tag: &str, known_tags: &[&'static str]) -> Option<&'static str> {
+    if let Some(known_tag) = known_tags
+        .iter()
+        .find(|known_tag| known_tag.eq_ignore_ascii_case(tag))
+    {
+        return Some(known_tag);
+    }
+
+    known_tags
+        .iter()
+        .map(|known_tag| (get_edit_distance(tag, known_tag), *known_tag))
+        .filter(|&(distance, _)| {
+            distance <= MAX_SUGGESTION_DISTANCE && distance * 3 <= tag.chars().count()
+        })
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, known_tag)| known_tag)
+}
+
+/// How many characters have to be inserted, removed or replaced to turn a into b.
+fn get_edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // The distances from the part of a seen so far to every prefix of b.
+    let mut distances: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous_diagonal = distances[0];
+        distances[0] = i + 1;
+
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous_diagonal + usize::from(a_char != b_char);
+            previous_diagonal = distances[j + 1];
+            distances[j + 1] = substitution
+                .min(distances[j] + 1)
+                .min(previous_diagonal + 1);
+        }
+    }
+
+    distances[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TAGS: [&str; 5] = ["-D", "-L", "--follow", "--size", "--sort"];
+
+    #[test]
+    fn test_get_edit_distance() {
+        assert_eq!(get_edit_distance("", ""), 0);
+        assert_eq!(get_edit_distance("--folow", "--follow"), 1);
+        assert_eq!(get_edit_distance("kitten", "sitting"), 3);
+        assert_eq!(get_edit_distance("-D", ""), 2);
+    }
+
+    #[test]
+    fn test_suggest_tag() {
+        assert_eq!(suggest_tag("-d", &TAGS), Some("-D"));
+        assert_eq!(suggest_tag("--folow", &TAGS), Some("--follow"));
+        assert_eq!(suggest_tag("--sise", &TAGS), Some("--size"));
+        assert_eq!(suggest_tag("-x", &TAGS), None);
+        assert_eq!(suggest_tag("--colour", &TAGS), None);
+    }
+
+    #[test]
+    fn test_display_unknown_tag() {
+        assert_eq!(
+            ConfigError::unknown_tag("-d", &TAGS).to_string(),
+            "The tag `-d` is invalid, did you mean `-D`?"
+        );
+        assert_eq!(
+            ConfigError::unknown_tag("-x", &TAGS).to_string(),
+            "The tag `-x` is invalid."
+        );
+    }
+}
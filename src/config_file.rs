@@ -0,0 +1,354 @@
+//! Default options for the tree, so the same `-D 4 -L 20` doesn't have to be typed every day.
+//!
+//! They are read from `$XDG_CONFIG_HOME/mtree/config.toml`, then from the `.mtree.toml`
+//! closest to the base directory, then from `MTREE_OPTS`, and the command line comes last
+//! so it wins. Patterns add up instead. A config file holds the long names of the options
+//! without the dashes:
+//!
+//! ```toml
+//! depth = 4
+//! dir-limit = 20
+//! ignore = ["target", "*.lock"]
+//! gitignore = true
+//! ```
+//!
+//! `key = false` turns a flag set by an earlier file off again, like `--no-follow` on the
+//! command line, and is an error for the options without a `--no-` form. Only the options of the tree can be given, `mtree du`, `spec` and `verify`
+//! don't read any defaults.
+//!
+//! Only the part of TOML these need is understood: keys at the top with a boolean, a
+//! number, a string or a list of those on one line.
+use super::config::NEGATED_FLAGS;
+use super::config_error::ConfigError;
+use super::update_file::split_flags;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const PROJECT_FILE_NAME: &str = ".mtree.toml";
+
+/// The keys of the options that only have a short tag.
+const SHORT_TAG_KEYS: [(&str, &str); 6] = [
+    ("ignore", "-I"),
+    ("only", "-P"),
+    ("human", "-h"),
+    ("permissions", "-p"),
+    ("owner", "-u"),
+    ("group", "-g"),
+];
+
+/// Options from one of the places defaults come from, as command line arguments.
+pub struct DefaultArgs {
+    /// The path of the file, or `MTREE_OPTS`, for errors.
+    pub source: String,
+    pub args: Vec<String>,
+}
+
+/// Every default that applies to the tree of the base directory, least important first.
+pub fn get_default_args(root_dir: &Path) -> Result<Vec<DefaultArgs>, ConfigError> {
+    let config_files = [get_user_config_file(), find_project_config_file(root_dir)];
+
+    let mut defaults = Vec::new();
+    for path in config_files.into_iter().flatten() {
+        defaults.extend(read_config_file(&path)?);
+    }
+
+    if let Some(options) = env::var_os("MTREE_OPTS") {
+        let source = "MTREE_OPTS".to_string();
+        let args =
+            split_flags(&options.to_string_lossy()).map_err(|error| ConfigError::InSource {
+                source: source.clone(),
+                error: Box::new(ConfigError::Invalid(error)),
+            })?;
+        defaults.push(DefaultArgs { source, args });
+    }
+
+    Ok(defaults)
+}
+
+/// `$XDG_CONFIG_HOME/mtree/config.toml`, or `~/.config/mtree/config.toml` without it.
+fn get_user_config_file() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+
+    Some(config_home.join("mtree").join("config.toml"))
+}
+
+/// The `.mtree.toml` in the base directory or the closest directory above it.
+fn find_project_config_file(root_dir: &Path) -> Option<PathBuf> {
+    let root_dir = fs::canonicalize(root_dir).ok()?;
+
+    root_dir
+        .ancestors()
+        .map(|directory| directory.join(PROJECT_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Nothing when the file doesn't exist.
+fn read_config_file(path: &Path) -> Result<Option<DefaultArgs>, ConfigError> {
+    let source = path.to_string_lossy().into_owned();
+    let in_source = |error: String| ConfigError::InSource {
+        source: source.clone(),
+        error: Box::new(ConfigError::Invalid(error)),
+    };
+
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(in_source(error.to_string())),
+    };
+
+    let args = parse_config_file(&contents).map_err(in_source)?;
+    Ok(Some(DefaultArgs { source, args }))
+}
+
+#[derive(Debug, PartialEq)]
+enum Value {
+    Bool(bool),
+    /// Numbers are passed on the way they were written, the tag checks them.
+    Text(String),
+    List(Vec<Value>),
+}
+
+/// Turns every key into arguments, `depth = 4` into `--depth=4`.
+fn parse_config_file(contents: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        push_line_args(&mut args, line).map_err(|error| format!("Line {}: {error}", index + 1))?;
+    }
+
+    Ok(args)
+}
+
+fn push_line_args(args: &mut Vec<String>, line: &str) -> Result<(), String> {
+    if line.starts_with('[') {
+        return Err("Tables aren't supported, every option is a key at the top.".to_string());
+    }
+
+    let (key, value) = line
+        .split_once('=')
+        .ok_or_else(|| "Expected `key = value`.".to_string())?;
+
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid key `{key}`."));
+    }
+
+    let tag = SHORT_TAG_KEYS
+        .iter()
+        .find(|(short_tag_key, _)| *short_tag_key == key)
+        .map_or(format!("--{key}"), |(_, short_tag)| short_tag.to_string());
+
+    match parse_value(value)? {
+        Value::Bool(true) => args.push(tag),
+        Value::Bool(false) => {
+            let negated_tag = format!("--no-{key}");
+            if !NEGATED_FLAGS.contains(&negated_tag.as_str()) {
+                return Err(format!("`{key}` can't be turned off."));
+            }
+            args.push(negated_tag);
+        }
+        Value::Text(text) => args.push(join_tag_and_value(&tag, &text)?),
+        Value::List(values) => {
+            for value in values {
+                let Value::Text(text) = value else {
+                    return Err(format!(
+                        "The list of `{key}` can only hold strings and numbers."
+                    ));
+                };
+                args.push(join_tag_and_value(&tag, &text)?);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `--depth=4` or `-I*.lock`, so the value can't be mistaken for anything else.
+fn join_tag_and_value(tag: &str, value: &str) -> Result<String, String> {
+    if tag.starts_with("--") {
+        Ok(format!("{tag}={value}"))
+    } else if value.is_empty() {
+        Err(format!("The value of `{tag}` can't be empty."))
+    } else {
+        Ok(format!("{tag}{value}"))
+    }
+}
+
+/// The value of a key, followed by nothing but a comment.
+fn parse_value(text: &str) -> Result<Value, String> {
+    let mut rest = text.trim_start();
+    let value = parse_next_value(&mut rest)?;
+
+    let rest = rest.trim_start();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err(format!("Unexpected `{rest}` after the value."));
+    }
+
+    Ok(value)
+}
+
+/// Reads a value from the start of rest, leaving what comes after it.
+fn parse_next_value(rest: &mut &str) -> Result<Value, String> {
+    *rest = rest.trim_start();
+
+    if let Some(after_quote) = rest.strip_prefix('"') {
+        return parse_basic_string(after_quote, rest).map(Value::Text);
+    }
+
+    if let Some(after_quote) = rest.strip_prefix('\'') {
+        // Literal strings have no escapes at all.
+        let end = after_quote
+            .find('\'')
+            .ok_or_else(|| "The string has no closing `'`.".to_string())?;
+        let text = after_quote[..end].to_string();
+        *rest = &after_quote[end + 1..];
+        return Ok(Value::Text(text));
+    }
+
+    if let Some(after_bracket) = rest.strip_prefix('[') {
+        *rest = after_bracket;
+        return parse_list(rest);
+    }
+
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || "+-._".contains(c)))
+        .unwrap_or(rest.len());
+    let word = &rest[..end];
+    *rest = &rest[end..];
+
+    match word {
+        "true" => Ok(Value::Bool(true)),
+        "false" => Ok(Value::Bool(false)),
+        _ if !word.is_empty() && word.replace('_', "").parse::<f64>().is_ok() => {
+            Ok(Value::Text(word.replace('_', "")))
+        }
+        _ => Err(format!(
+            "Invalid value `{word}`, strings need to be in quotes."
+        )),
+    }
+}
+
+/// The part of a `"string"` after the opening quote, with the escapes of TOML.
+fn parse_basic_string<'a>(text: &'a str, rest: &mut &'a str) -> Result<String, String> {
+    let mut string = String::new();
+    let mut chars = text.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => {
+                *rest = &text[index + 1..];
+                return Ok(string);
+            }
+            '\\' => match chars.next().map(|(_, escaped)| escaped) {
+                Some('"') => string.push('"'),
+                Some('\\') => string.push('\\'),
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                Some(escaped) => return Err(format!("The escape `\\{escaped}` isn't supported.")),
+                None => break,
+            },
+            c => string.push(c),
+        }
+    }
+
+    Err("The string has no closing `\"`.".to_string())
+}
+
+/// The part of a `[list]` after the opening bracket, all on one line.
+fn parse_list(rest: &mut &str) -> Result<Value, String> {
+    let mut values = Vec::new();
+
+    loop {
+        *rest = rest.trim_start();
+        if let Some(after_bracket) = rest.strip_prefix(']') {
+            *rest = after_bracket;
+            return Ok(Value::List(values));
+        }
+
+        values.push(parse_next_value(rest)?);
+
+        *rest = rest.trim_start();
+        if let Some(after_comma) = rest.strip_prefix(',') {
+            *rest = after_comma;
+        } else if !rest.starts_with(']') {
+            return Err(
+                "Expected `,` or `]` in the list, lists have to be on one line.".to_string(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config_file() {
+        let contents = "# My defaults\n\
+            depth = 4\n\
+            dir-limit = 20 # per directory\n\
+            ignore = [\"target\", '*.lock']\n\
+            gitignore = true\n\
+            follow = false\n\
+            dir-hash = true\n\
+            sort = \"size\"\n";
+
+        assert_eq!(
+            parse_config_file(contents),
+            Ok(vec![
+                "--depth=4".to_string(),
+                "--dir-limit=20".to_string(),
+                "-Itarget".to_string(),
+                "-I*.lock".to_string(),
+                "--gitignore".to_string(),
+                "--no-follow".to_string(),
+                "--dir-hash".to_string(),
+                "--sort=size".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value(" 1_000"), Ok(Value::Text("1000".to_string())));
+        assert_eq!(parse_value("2.5"), Ok(Value::Text("2.5".to_string())));
+        assert_eq!(
+            parse_value(r#""say \"hi\"""#),
+            Ok(Value::Text("say \"hi\"".to_string()))
+        );
+        assert_eq!(parse_value("[]"), Ok(Value::List(Vec::new())));
+        assert!(parse_value("size").is_err());
+        assert!(parse_value("\"open").is_err());
+        assert!(parse_value("[\"a\",").is_err());
+        assert!(parse_value("4 4").is_err());
+    }
+
+    #[test]
+    fn test_parse_config_file_errors() {
+        for contents in [
+            "[tree]\ndepth = 4",
+            "depth",
+            "my key = 1",
+            "ignore = [[\"a\"]]",
+            "ignore = \"\"",
+            "dir-hash = false",
+            "permissions = false",
+            "depth = false",
+        ] {
+            assert!(parse_config_file(contents).is_err(), "{contents}");
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! `--dir-hash` gives every directory a hash of everything inside of it, so comparing two
+//! hashes says whether anything below a directory changed, like a Merkle tree.
+//!
+//! A directory is hashed from a record for each of its entries, sorted by name:
+//!
+//! ```text
+//! <type> <mode in octal> <hash>\t<name>\0
+//! ```
+//!
+//! where the hash is the digest of the contents for a file, of the target for a symlink,
+//! the hash of the directory for a sub directory, `unreadable` for what couldn't be read
+//! and empty for anything else. The mode is 0 where there are no unix permissions. An entry
+//! whose type can't even be read is left out. Both are counted in the errors, only a base
+//! directory that can't be listed has no hash at all.
+//!
+//! Entries hidden by the filters, the ignore files or a `--dir-hash-exclude` pattern are
+//! left out, the `-D`, `-L` and `-T` limits are not.
+use super::config::Config;
+use super::filter::EntryFilter;
+use super::glob::Glob;
+use super::hash::{hash_bytes, hash_file, HashAlgorithm, HashStats};
+use super::output::EntryKind;
+use super::walk_error::{ErrorTally, WalkError};
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DirHashMode {
+    Off,
+    /// Shows the hash next to every directory in the tree.
+    Tree,
+    /// Only prints the hash of the base directory, for scripts.
+    RootOnly,
+}
+
+/// Set with `--dir-hash`, `--root-hash` and `--dir-hash-exclude`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DirHashOptions {
+    pub mode: DirHashMode,
+    /// Paths matching any of these don't count towards the hashes.
+    pub exclude: Vec<Glob>,
+}
+
+impl DirHashOptions {
+    pub fn new() -> Self {
+        Self {
+            mode: DirHashMode::Off,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+/// Hashes a whole tree up front, remembering the digest of every directory and file in it.
+pub struct DirHasher {
+    algorithm: HashAlgorithm,
+    exclude: Vec<Glob>,
+    entry_filter: EntryFilter,
+    digests: HashMap<PathBuf, String>,
+    hash_stats: HashStats,
+    errors: ErrorTally,
+}
+
+impl DirHasher {
+    /// Uses the algorithm of `--hash`, or sha256 without it.
+    pub fn new(config: &Config, root_path: PathBuf) -> Self {
+        let options = config
+            .get_dir_hash_options()
+            .expect("Config should be in render mode.");
+        let filter_options = config
+            .get_filter_options()
+            .expect("Config should be in render mode.");
+
+        Self {
+            algorithm: config.get_hash_algorithm().unwrap_or(HashAlgorithm::Sha256),
+            exclude: options.exclude,
+            entry_filter: EntryFilter::new(filter_options, root_path),
+            digests: HashMap::new(),
+            hash_stats: HashStats::default(),
+            errors: ErrorTally::default(),
+        }
+    }
+
+    /// `--root-hash`, the hash of the base directory as plain hex, with what was read and
+    /// what couldn't be.
+    pub fn get_root_hash(
+        config: &Config,
+        root_dir: &str,
+    ) -> io::Result<(String, HashStats, ErrorTally)> {
+        let root_path = PathBuf::from(root_dir);
+
+        let mut dir_hasher = Self::new(config, root_path.clone());
+        let hex = dir_hasher.hash_directory(&root_path)?;
+        Ok((hex, dir_hasher.hash_stats, dir_hasher.errors))
+    }
+
+    /// Hashes the directory at path and everything below it, giving `algorithm:hex`.
+    pub fn hash_tree(&mut self, path: &Path) -> io::Result<String> {
+        let hex = self.hash_directory(path)?;
+        Ok(self.to_digest(&hex))
+    }
+
+    /// The digest of a directory or file hashed by `hash_tree`, as `algorithm:hex`.
+    pub fn get_digest(&self, path: &Path) -> Option<String> {
+        self.digests.get(path).map(|hex| self.to_digest(hex))
+    }
+
+    pub fn get_hash_stats(&self) -> HashStats {
+        self.hash_stats
+    }
+
+    fn to_digest(&self, hex: &str) -> String {
+        format!("{}:{hex}", self.algorithm.get_name())
+    }
+
+    /// Only fails when the directory itself can't be listed, anything inside it that can't be
+    /// read is counted in the errors instead.
+    fn hash_directory(&mut self, path: &Path) -> io::Result<String> {
+        let mut entries: Vec<fs::DirEntry> = Vec::new();
+        for entry in fs::read_dir(path)? {
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(error) => self.errors.add(WalkError::from_io_error(&error)),
+            }
+        }
+        entries.sort_by_key(|entry| entry.file_name());
+
+        self.entry_filter.enter_directory(path);
+
+        let mut records: Vec<u8> = Vec::new();
+
+        for entry in entries {
+            let entry_path = entry.path();
+            let metadata = match fs::symlink_metadata(&entry_path) {
+                Ok(metadata) => metadata,
+                Err(error) => {
+                    self.errors.add(WalkError::from_io_error(&error));
+                    continue;
+                }
+            };
+            let kind = EntryKind::from_file_type(Some(metadata.file_type()));
+            let is_dir = kind == EntryKind::Directory;
+
+            if !self.entry_filter.is_visible(&entry_path, is_dir) || self.is_excluded(&entry_path) {
+                continue;
+            }
+
+            let hash = match kind {
+                EntryKind::Directory => self.hash_directory(&entry_path),
+                EntryKind::File => hash_file(&entry_path, self.algorithm, &mut self.hash_stats),
+                EntryKind::Symlink => fs::read_link(&entry_path).map(|target| {
+                    hash_bytes(self.algorithm, target.as_os_str().as_encoded_bytes())
+                }),
+                EntryKind::Other => Ok(String::new()),
+            };
+
+            // Whatever can't be read still changes the hash of its parent, the same way every time.
+            let hash = match hash {
+                Ok(hash) => {
+                    self.digests.insert(entry_path, hash.clone());
+                    hash
+                }
+                Err(error) => {
+                    self.errors.add(WalkError::from_io_error(&error));
+                    "unreadable".to_string()
+                }
+            };
+
+            records.extend_from_slice(
+                format!("{} {:o} {hash}\t", kind.get_name(), get_mode(&metadata)).as_bytes(),
+            );
+            records.extend_from_slice(entry.file_name().as_encoded_bytes());
+            records.push(0);
+        }
+
+        self.entry_filter.leave_directory();
+
+        let hex = hash_bytes(self.algorithm, &records);
+        self.digests.insert(path.to_path_buf(), hex.clone());
+        Ok(hex)
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        let relative_path = self.entry_filter.get_relative_path(path);
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        self.exclude
+            .iter()
+            .any(|glob| glob.matches(&relative_path, &name))
+    }
+}
+
+#[cfg(unix)]
+fn get_mode(metadata: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn get_mode(_metadata: &fs::Metadata) -> u32 {
+    0
+}
@@ -0,0 +1,278 @@
+//! `mtree du` shows how the space of every directory is split between its entries, largest
+//! first, to find out what is filling up a disk:
+//!
+//! ```text
+//! 100.0% ██████████    3.1 GiB  .
+//!  42.1% ████░░░░░░    1.3 GiB  ├── target ...
+//!  31.0% ███░░░░░░░  987.2 MiB  ├── .git ...
+//!   1.2% ░░░░░░░░░░   38.4 MiB  └── other (37 items)
+//! ```
+//!
+//! Every percentage is the share of the directory the entry is in. Entries under the
+//! `--threshold` share are put together on the `other` line, and directories at the `-D`
+//! limit are added up without being listed.
+use super::config::Config;
+use super::filter::EntryFilter;
+use super::quote::{quote_name, QuoteStyle};
+use super::size::{format_size_with_unit, SizeCalculator, SizeUnits};
+use super::utils::format_count_of;
+use super::walk_error::{ErrorTally, WalkError};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// How many cells the bar of an entry has, each one is a tenth of its parent.
+const BAR_WIDTH: usize = 10;
+
+/// How wide the percentage, bar and size in front of the tree are, with their spaces.
+const COLUMNS_WIDTH: usize = 6 + 1 + BAR_WIDTH + 1 + 10 + 2;
+
+/// Writes the disk usage of the base directory, down to the `-D` limit, giving back the
+/// errors the walk ran into.
+pub fn write_disk_usage(config: &Config, writer: &mut dyn Write) -> io::Result<ErrorTally> {
+    let root_dir = config.get_root_dir().expect("Config should be in du mode.");
+    let root_path = PathBuf::from(&root_dir);
+    let filter_options = config
+        .get_filter_options()
+        .expect("Config should be in du mode.");
+    let size_options = config
+        .get_size_options()
+        .expect("Config should be in du mode.");
+
+    let mut disk_usage = DiskUsage {
+        writer,
+        size_calculator: SizeCalculator::new(
+            size_options,
+            filter_options.clone(),
+            root_path.clone(),
+            false,
+        ),
+        entry_filter: EntryFilter::new(filter_options, root_path.clone()),
+        units: size_options.units,
+        threshold: config
+            .get_du_threshold()
+            .expect("Config should be in du mode."),
+        max_depth: config.get_max_depth(),
+        quote_style: config
+            .get_quote_style()
+            .expect("Config should be in du mode."),
+        draw_layer_table: Vec::new(),
+    };
+
+    // Asking for the base directory first adds up the whole tree, see `SizeCalculator`.
+    let total = disk_usage.size_calculator.get_total_size(&root_path);
+    let root_name = quote_name(root_path.as_os_str(), disk_usage.quote_style);
+    disk_usage.write_line(total, total, "", &root_name)?;
+    disk_usage.write_directory(&root_path, total, 0)?;
+    disk_usage.writer.flush()?;
+
+    // Every directory was already read by the size calculator, which counted what couldn't be.
+    Ok(disk_usage.size_calculator.get_errors())
+}
+
+/// An entry of a directory together with everything inside of it.
+struct Usage {
+    name: String,
+    path: PathBuf,
+    size: u64,
+    is_dir: bool,
+}
+
+struct DiskUsage<'a> {
+    writer: &'a mut dyn Write,
+    size_calculator: SizeCalculator,
+    entry_filter: EntryFilter,
+    units: SizeUnits,
+    threshold: f64,
+    max_depth: Option<usize>,
+    quote_style: QuoteStyle,
+    // Whether the directory at each depth has more entries to come, like the text tree.
+    draw_layer_table: Vec<bool>,
+}
+
+impl DiskUsage<'_> {
+    /// Lists the entries of the directory at path, which adds up to total.
+    fn write_directory(&mut self, path: &Path, total: u64, depth: usize) -> io::Result<()> {
+        if self.max_depth.is_some_and(|max_depth| depth >= max_depth) {
+            return Ok(());
+        }
+
+        let read_dir = match fs::read_dir(path) {
+            Ok(read_dir) => read_dir,
+            Err(error) => {
+                let prefix = self.get_branch_prefix(depth, true);
+                let message = WalkError::from_io_error(&error).get_message();
+                return writeln!(self.writer, "{:COLUMNS_WIDTH$}{prefix}[{message}]", "");
+            }
+        };
+
+        self.entry_filter.enter_directory(path);
+
+        let mut usages: Vec<Usage> = Vec::new();
+        for entry in read_dir.flatten() {
+            let entry_path = entry.path();
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+
+            if !self.entry_filter.is_visible(&entry_path, is_dir) {
+                continue;
+            }
+
+            usages.push(Usage {
+                name: quote_name(&entry.file_name(), self.quote_style),
+                size: self.size_calculator.get_total_size(&entry_path),
+                path: entry_path,
+                is_dir,
+            });
+        }
+
+        usages.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+        let other = collapse_small_entries(&mut usages, total, self.threshold);
+
+        if depth == self.draw_layer_table.len() {
+            self.draw_layer_table.push(true);
+        }
+
+        let line_count = usages.len() + usize::from(other.is_some());
+
+        for (index, usage) in usages.iter().enumerate() {
+            let is_last_in_dir = index + 1 == line_count;
+            self.draw_layer_table[depth] = !is_last_in_dir;
+
+            let prefix = self.get_branch_prefix(depth, is_last_in_dir);
+            let is_at_depth_limit = usage.is_dir
+                && self
+                    .max_depth
+                    .is_some_and(|max_depth| depth + 1 >= max_depth);
+
+            if is_at_depth_limit {
+                self.write_line(usage.size, total, &prefix, &format!("{} ...", usage.name))?;
+            } else {
+                self.write_line(usage.size, total, &prefix, &usage.name)?;
+            }
+
+            if usage.is_dir {
+                self.write_directory(&usage.path, usage.size, depth + 1)?;
+            }
+        }
+
+        if let Some((count, size)) = other {
+            let prefix = self.get_branch_prefix(depth, true);
+            let name = format!("other ({})", format_count_of(count, "item", "items"));
+            self.write_line(size, total, &prefix, &name)?;
+        }
+
+        self.entry_filter.leave_directory();
+
+        Ok(())
+    }
+
+    /// ` 42.1% ████░░░░░░    1.3 GiB  ├── target`.
+    fn write_line(&mut self, size: u64, total: u64, prefix: &str, name: &str) -> io::Result<()> {
+        let percentage = get_percentage(size, total);
+
+        writeln!(
+            self.writer,
+            "{percentage:>5.1}% {} {:>10}  {prefix}{name}",
+            get_bar(percentage),
+            format_size_with_unit(size, self.units),
+        )
+    }
+
+    /// Builds the `│   ├── ` part of a line for an entry at depth.
+    fn get_branch_prefix(&self, depth: usize, is_last_in_dir: bool) -> String {
+        let mut prefix = String::new();
+
+        for &render_layer in self.draw_layer_table.iter().take(depth) {
+            if render_layer {
+                prefix.push_str("│   ");
+            } else {
+                prefix.push_str("    ");
+            }
+        }
+
+        if is_last_in_dir {
+            prefix.push_str("└── ");
+        } else {
+            prefix.push_str("├── ");
+        }
+
+        prefix
+    }
+}
+
+/// Takes the entries under the threshold percentage of the total out of the list, giving
+/// how many there were and how big they are together. A single small entry is left in, an
+/// `other` line wouldn't make the list any shorter.
+fn collapse_small_entries(
+    usages: &mut Vec<Usage>,
+    total: u64,
+    threshold: f64,
+) -> Option<(usize, u64)> {
+    let is_small = |usage: &Usage| get_percentage(usage.size, total) < threshold;
+
+    let count = usages.iter().filter(|usage| is_small(usage)).count();
+    if count < 2 {
+        return None;
+    }
+
+    let size = usages
+        .iter()
+        .filter(|usage| is_small(usage))
+        .map(|usage| usage.size)
+        .sum();
+    usages.retain(|usage| !is_small(usage));
+
+    Some((count, size))
+}
+
+fn get_percentage(size: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    size as f64 / total as f64 * 100.0
+}
+
+/// `████░░░░░░` for 42%, rounded to the nearest cell.
+fn get_bar(percentage: f64) -> String {
+    let filled = ((percentage / 100.0 * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+    format!("{}{}", "█".repeat(filled), "░".repeat(BAR_WIDTH - filled))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_usage(name: &str, size: u64) -> Usage {
+        Usage {
+            name: name.to_string(),
+            path: PathBuf::from(name),
+            size,
+            is_dir: false,
+        }
+    }
+
+    #[test]
+    fn test_get_bar() {
+        assert_eq!(get_bar(0.0), "░░░░░░░░░░");
+        assert_eq!(get_bar(42.1), "████░░░░░░");
+        assert_eq!(get_bar(100.0), "██████████");
+    }
+
+    #[test]
+    fn test_collapse_small_entries() {
+        let mut usages = vec![
+            get_usage("big", 900),
+            get_usage("small", 5),
+            get_usage("tiny", 1),
+        ];
+        assert_eq!(collapse_small_entries(&mut usages, 1000, 1.0), Some((2, 6)));
+        assert_eq!(usages.len(), 1);
+
+        let mut usages = vec![get_usage("big", 900), get_usage("small", 5)];
+        assert_eq!(collapse_small_entries(&mut usages, 1000, 1.0), None);
+        assert_eq!(usages.len(), 2);
+
+        assert_eq!(get_percentage(5, 0), 0.0);
+    }
+}
@@ -0,0 +1,95 @@
+use super::glob::Glob;
+use super::ignore::IgnoreStack;
+use super::utils::to_slash_path;
+use std::path::{Path, PathBuf};
+
+/// Decides which entries show up in the tree, set with `-I`, `-P`, `--prune` and `--gitignore`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct FilterOptions {
+    /// Entries matching any of these are left out, along with everything inside them.
+    pub ignore: Vec<Glob>,
+    /// When not empty, only files matching one of these are shown. Directories are still walked.
+    pub only: Vec<Glob>,
+    /// Leave out directories that end up with nothing to show inside them.
+    pub prune: bool,
+    /// Also honor `.gitignore`, `.ignore` and `.mtreeignore` files, `.git/info/exclude` and
+    /// the global git excludes file.
+    pub use_gitignore: bool,
+}
+
+impl FilterOptions {
+    pub fn new() -> Self {
+        Self {
+            ignore: Vec::new(),
+            only: Vec::new(),
+            prune: false,
+            use_gitignore: false,
+        }
+    }
+
+    /// Checks an entry against the patterns, `relative_path` is relative to the base directory.
+    pub fn is_visible(&self, relative_path: &str, name: &str, is_dir: bool) -> bool {
+        if self
+            .ignore
+            .iter()
+            .any(|glob| glob.matches(relative_path, name))
+        {
+            return false;
+        }
+
+        is_dir
+            || self.only.is_empty()
+            || self
+                .only
+                .iter()
+                .any(|glob| glob.matches(relative_path, name))
+    }
+}
+
+/// Applies the filter options and the ignore files while walking down from `root_path`.
+pub struct EntryFilter {
+    options: FilterOptions,
+    root_path: PathBuf,
+    ignore_stack: IgnoreStack,
+}
+
+impl EntryFilter {
+    pub fn new(options: FilterOptions, root_path: PathBuf) -> Self {
+        let ignore_stack = IgnoreStack::new(&root_path, options.use_gitignore);
+
+        Self {
+            options,
+            root_path,
+            ignore_stack,
+        }
+    }
+
+    pub fn get_options(&self) -> &FilterOptions {
+        &self.options
+    }
+
+    /// The ignore files of a directory apply to everything below it, until it is left again.
+    pub fn enter_directory(&mut self, path: &Path) {
+        self.ignore_stack.enter_directory(path);
+    }
+
+    pub fn leave_directory(&mut self) {
+        self.ignore_stack.leave_directory();
+    }
+
+    /// The path relative to the base directory, separated by `/` on every platform.
+    pub fn get_relative_path(&self, path: &Path) -> String {
+        to_slash_path(path.strip_prefix(&self.root_path).unwrap_or(path))
+    }
+
+    pub fn is_visible(&self, path: &Path, is_dir: bool) -> bool {
+        let relative_path = self.get_relative_path(path);
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        self.options.is_visible(&relative_path, &name, is_dir)
+            && !self.ignore_stack.is_ignored(path, is_dir)
+    }
+}
@@ -0,0 +1,269 @@
+/// A shell style wildcard pattern.
+///
+/// - `*` matches any run of characters except `/`
+/// - `**` matches any run of characters including `/`
+/// - `?` matches a single character except `/`
+/// - `[abc]`, `[a-z]` and `[!abc]` (or `[^abc]`) match one character from a set
+/// - `\` makes the next character match literally
+/// - `a|b` matches either `a` or `b`
+///
+/// A pattern without a `/` is matched against the name of an entry, anything
+/// else is matched against the path of the entry relative to the base directory.
+///
+/// # Examples
+///
+/// ```
+/// let glob = Glob::new("*.rs").unwrap();
+/// assert!(glob.matches("src/main.rs", "main.rs"));
+/// assert!(!glob.matches("Cargo.toml", "Cargo.toml"));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct Glob {
+    pattern: String,
+    alternatives: Vec<Vec<Token>>,
+    match_path: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum Token {
+    Literal(char),
+    AnyChar,
+    AnyRun,
+    AnyPath,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        Self::build(pattern, split_alternatives(pattern), pattern.contains('/'))
+    }
+
+    /// A pattern from an ignore file, where `|` has no special meaning and whether
+    /// the pattern is matched against the path is decided by the ignore file syntax.
+    pub fn new_gitignore(pattern: &str, anchored: bool) -> Result<Self, String> {
+        Self::build(pattern, vec![pattern.to_string()], anchored)
+    }
+
+    fn build(pattern: &str, alternatives: Vec<String>, match_path: bool) -> Result<Self, String> {
+        let alternatives = alternatives
+            .iter()
+            .map(|alternative| tokenize(alternative))
+            .collect::<Result<Vec<_>, String>>()
+            .map_err(|error| format!("Invalid pattern `{pattern}`: {error}."))?;
+
+        Ok(Self {
+            pattern: pattern.to_string(),
+            alternatives,
+            match_path,
+        })
+    }
+
+    /// The pattern as it was given.
+    pub fn get_pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Checks the pattern against an entry, `relative_path` uses `/` as the separator.
+    pub fn matches(&self, relative_path: &str, name: &str) -> bool {
+        let text: Vec<char> = if self.match_path {
+            relative_path.chars().collect()
+        } else {
+            name.chars().collect()
+        };
+
+        self.alternatives
+            .iter()
+            .any(|tokens| match_tokens(tokens, &text))
+    }
+}
+
+/// Splits `a|b` into its alternatives, a `|` inside `[...]` or after `\` doesn't count.
+fn split_alternatives(pattern: &str) -> Vec<String> {
+    let mut alternatives = vec![String::new()];
+    let mut chars = pattern.chars();
+    let mut in_class = false;
+
+    while let Some(c) = chars.next() {
+        let current = alternatives
+            .last_mut()
+            .expect("There is always one alternative.");
+        match c {
+            '\\' => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            '[' if !in_class => {
+                in_class = true;
+                current.push(c);
+            }
+            ']' if in_class => {
+                in_class = false;
+                current.push(c);
+            }
+            '|' if !in_class => alternatives.push(String::new()),
+            _ => current.push(c),
+        }
+    }
+
+    alternatives
+}
+
+fn tokenize(pattern: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        match chars[index] {
+            '\\' => {
+                let escaped = chars
+                    .get(index + 1)
+                    .ok_or_else(|| "trailing `\\`".to_string())?;
+                tokens.push(Token::Literal(*escaped));
+                index += 2;
+            }
+            '*' => {
+                if chars.get(index + 1) == Some(&'*') {
+                    tokens.push(Token::AnyPath);
+                    index += 2;
+                    // `**/` also matches zero directories, so `**/a` matches `a`.
+                    if chars.get(index) == Some(&'/') {
+                        index += 1;
+                    }
+                } else {
+                    tokens.push(Token::AnyRun);
+                    index += 1;
+                }
+            }
+            '?' => {
+                tokens.push(Token::AnyChar);
+                index += 1;
+            }
+            '[' => {
+                let (token, next_index) = parse_class(&chars, index)?;
+                tokens.push(token);
+                index = next_index;
+            }
+            c => {
+                tokens.push(Token::Literal(c));
+                index += 1;
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Parses a `[...]` class starting at `start`, returns the token and the index after the `]`.
+fn parse_class(chars: &[char], start: usize) -> Result<(Token, usize), String> {
+    let mut index = start + 1;
+    let negated = matches!(chars.get(index), Some('!') | Some('^'));
+    if negated {
+        index += 1;
+    }
+
+    let mut ranges = Vec::new();
+    let mut is_first = true;
+
+    loop {
+        let c = *chars.get(index).ok_or_else(|| "unclosed `[`".to_string())?;
+
+        // A `]` right after the opening bracket is a literal `]`.
+        if c == ']' && !is_first {
+            return Ok((Token::Class { negated, ranges }, index + 1));
+        }
+        is_first = false;
+
+        let low = if c == '\\' {
+            index += 1;
+            *chars.get(index).ok_or_else(|| "unclosed `[`".to_string())?
+        } else {
+            c
+        };
+        index += 1;
+
+        if chars.get(index) == Some(&'-') && chars.get(index + 1).is_some_and(|&c| c != ']') {
+            let high = chars[index + 1];
+            ranges.push((low, high));
+            index += 2;
+        } else {
+            ranges.push((low, low));
+        }
+    }
+}
+
+fn match_tokens(tokens: &[Token], text: &[char]) -> bool {
+    let Some((token, rest)) = tokens.split_first() else {
+        return text.is_empty();
+    };
+
+    match token {
+        Token::AnyRun => (0..=text.len())
+            .take_while(|&skip| skip == 0 || text[skip - 1] != '/')
+            .any(|skip| match_tokens(rest, &text[skip..])),
+        Token::AnyPath => (0..=text.len()).any(|skip| match_tokens(rest, &text[skip..])),
+        Token::AnyChar => !text.is_empty() && text[0] != '/' && match_tokens(rest, &text[1..]),
+        Token::Literal(c) => !text.is_empty() && text[0] == *c && match_tokens(rest, &text[1..]),
+        Token::Class { negated, ranges } => {
+            if text.is_empty() || text[0] == '/' {
+                return false;
+            }
+            let in_class = ranges
+                .iter()
+                .any(|&(low, high)| low <= text[0] && text[0] <= high);
+            in_class != *negated && match_tokens(rest, &text[1..])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches_name(pattern: &str, name: &str) -> bool {
+        Glob::new(pattern).unwrap().matches(name, name)
+    }
+
+    #[test]
+    fn test_glob_wildcards() {
+        assert!(matches_name("*.rs", "main.rs"));
+        assert!(!matches_name("*.rs", "main.rs.bak"));
+        assert!(matches_name("?.o", "a.o"));
+        assert!(!matches_name("?.o", "ab.o"));
+        assert!(matches_name("*", ""));
+    }
+
+    #[test]
+    fn test_glob_classes() {
+        assert!(matches_name("file[0-9]", "file7"));
+        assert!(!matches_name("file[0-9]", "filex"));
+        assert!(matches_name("file[!0-9]", "filex"));
+        assert!(matches_name("[]]", "]"));
+        assert!(Glob::new("file[0-9").is_err());
+    }
+
+    #[test]
+    fn test_glob_alternatives() {
+        assert!(matches_name("target|.git", "target"));
+        assert!(matches_name("target|.git", ".git"));
+        assert!(!matches_name("target|.git", "src"));
+        assert!(matches_name("[|]", "|"));
+    }
+
+    #[test]
+    fn test_glob_paths() {
+        let glob = Glob::new("src/*.rs").unwrap();
+        assert!(glob.matches("src/main.rs", "main.rs"));
+        assert!(!glob.matches("src/bin/main.rs", "main.rs"));
+
+        let glob = Glob::new("**/tests/*.rs").unwrap();
+        assert!(glob.matches("tests/a.rs", "a.rs"));
+        assert!(glob.matches("crates/core/tests/a.rs", "a.rs"));
+        assert!(!glob.matches("crates/core/tests/unit/a.rs", "a.rs"));
+    }
+}
@@ -0,0 +1,189 @@
+use super::utils::format_count;
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Files are read in chunks of this size, so hashing never holds a whole file in memory.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// How many hex digits of a digest are shown next to a name in the text tree.
+const SHORT_DIGEST_LEN: usize = 12;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+    Md5,
+}
+
+impl HashAlgorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(Self::Sha256),
+            "blake3" => Some(Self::Blake3),
+            "md5" => Some(Self::Md5),
+            _ => None,
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Blake3 => "blake3",
+            Self::Md5 => "md5",
+        }
+    }
+}
+
+/// An algorithm that is fed bytes a chunk at a time.
+enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Md5(Md5),
+}
+
+impl Hasher {
+    fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => Self::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Md5 => Self::Md5(Md5::new()),
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Sha256(hasher) => hasher.update(bytes),
+            Self::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+            Self::Md5(hasher) => hasher.update(bytes),
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            Self::Sha256(hasher) => to_hex(&hasher.finalize()),
+            Self::Blake3(hasher) => to_hex(hasher.finalize().as_bytes()),
+            Self::Md5(hasher) => to_hex(&hasher.finalize()),
+        }
+    }
+}
+
+/// The hex digest of some bytes, for things other than files.
+pub fn hash_bytes(algorithm: HashAlgorithm, bytes: &[u8]) -> String {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update(bytes);
+    hasher.finish()
+}
+
+/// How much was hashed and for how long, shown in verbose mode.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct HashStats {
+    pub files: usize,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl HashStats {
+    pub fn add(&mut self, other: &Self) {
+        self.files += other.files;
+        self.bytes += other.bytes;
+        self.elapsed += other.elapsed;
+    }
+
+    /// `1,234,567 bytes in 12 files hashed in 0.01 seconds (123.4 MB/s)`.
+    pub fn get_message(&self) -> String {
+        let seconds = self.elapsed.as_secs_f64();
+        let throughput = if seconds > 0.0 {
+            format!(" ({:.1} MB/s)", self.bytes as f64 / seconds / 1_000_000.0)
+        } else {
+            String::new()
+        };
+
+        format!(
+            "{} bytes in {} files hashed in {seconds:.2} seconds{throughput}",
+            format_count(self.bytes as usize),
+            format_count(self.files),
+        )
+    }
+}
+
+/// Streams the contents of a regular file through the hash. The caller makes sure it is
+/// a regular file, reading a fifo or a device could block or never end.
+pub fn hash_file(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    stats: &mut HashStats,
+) -> io::Result<String> {
+    let start_time = Instant::now();
+    let mut file = File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buffer = vec![0; BUFFER_SIZE];
+
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        hasher.update(&buffer[..read]);
+        stats.bytes += read as u64;
+    }
+
+    stats.files += 1;
+    stats.elapsed += start_time.elapsed();
+
+    Ok(hasher.finish())
+}
+
+/// `sha256:ab12cd34ef56…`, the start of a digest written as `algorithm:hex`.
+pub fn shorten_digest(digest: &str) -> String {
+    let Some((algorithm, hex)) = digest.split_once(':') else {
+        return digest.to_string();
+    };
+
+    if hex.len() <= SHORT_DIGEST_LEN {
+        return digest.to_string();
+    }
+
+    format!("{algorithm}:{}…", &hex[..SHORT_DIGEST_LEN])
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_bytes() {
+        assert_eq!(
+            hash_bytes(HashAlgorithm::Sha256, b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            hash_bytes(HashAlgorithm::Md5, b"abc"),
+            "900150983cd24fb0d6963f7d28e17f72"
+        );
+        assert_eq!(
+            hash_bytes(HashAlgorithm::Blake3, b"abc"),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+
+    #[test]
+    fn test_shorten_digest() {
+        assert_eq!(
+            shorten_digest("md5:900150983cd24fb0d6963f7d28e17f72"),
+            "md5:900150983cd2…"
+        );
+        assert_eq!(shorten_digest("md5:9001"), "md5:9001");
+    }
+}
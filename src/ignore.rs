@@ -0,0 +1,349 @@
+use super::glob::Glob;
+use super::utils::to_slash_path;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Ignore files read in every directory with `--gitignore`, lowest precedence first, ending
+/// with the one only mtree reads.
+///
+/// None of them are read without it: they belong to the tree being walked, and a spec or
+/// `mtree verify` can't let whoever writes to that tree decide what gets checked.
+const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", ".mtreeignore"];
+
+/// A single line of an ignore file, like `!/build/keep.txt` or `*.o`.
+#[derive(Debug)]
+struct IgnoreRule {
+    glob: Glob,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreRule {
+    /// Parses one line with the `.gitignore` syntax, blank lines and comments give `None`.
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line),
+        };
+
+        // `\!` and `\#` start patterns that really begin with those characters.
+        let pattern = match pattern.strip_prefix('\\') {
+            Some(rest) if rest.starts_with('!') || rest.starts_with('#') => rest,
+            _ => pattern,
+        };
+
+        // Trailing spaces are dropped unless they are escaped with `\`.
+        let mut pattern = pattern.to_string();
+        while pattern.ends_with(' ') && !pattern.ends_with("\\ ") {
+            pattern.pop();
+        }
+
+        let dir_only = pattern.ends_with('/');
+        let pattern = pattern.trim_end_matches('/');
+
+        // A slash anywhere but the end ties the pattern to the directory of the ignore file.
+        let anchored = pattern.contains('/');
+        let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let glob = Glob::new_gitignore(pattern, anchored).ok()?;
+
+        Some(Self {
+            glob,
+            negated,
+            dir_only,
+        })
+    }
+}
+
+/// The rules of one ignore file, matched relative to the directory in `base`.
+#[derive(Debug)]
+pub struct IgnoreFile {
+    base: PathBuf,
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreFile {
+    pub fn parse(base: PathBuf, contents: &str) -> Self {
+        Self {
+            base,
+            rules: contents.lines().filter_map(IgnoreRule::parse).collect(),
+        }
+    }
+
+    fn read(base: &Path, file: &Path) -> Option<Self> {
+        let contents = fs::read_to_string(file).ok()?;
+        let ignore_file = Self::parse(base.to_path_buf(), &contents);
+
+        if ignore_file.rules.is_empty() {
+            None
+        } else {
+            Some(ignore_file)
+        }
+    }
+
+    /// `Some(true)` if the last matching rule ignores the path, `Some(false)` if it
+    /// re-includes it with `!` and `None` if no rule matches at all.
+    pub fn check(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative_path = to_slash_path(path.strip_prefix(&self.base).ok()?);
+        let name = relative_path.rsplit('/').next().unwrap_or_default();
+
+        self.rules
+            .iter()
+            .rev()
+            .filter(|rule| is_dir || !rule.dir_only)
+            .find(|rule| rule.glob.matches(&relative_path, name))
+            .map(|rule| !rule.negated)
+    }
+}
+
+/// The ignore files that apply to the directory currently being walked.
+///
+/// Files pushed later take precedence, so the order from bottom to top is the global
+/// excludes file, `.git/info/exclude`, and then the ignore files of each directory
+/// from the repository root down to the directory being walked.
+pub struct IgnoreStack {
+    use_gitignore: bool,
+    root: PathBuf,
+    absolute_root: PathBuf,
+    files: Vec<IgnoreFile>,
+    // How many files each entered directory pushed, so leaving it pops the right amount.
+    pushed_counts: Vec<usize>,
+}
+
+impl IgnoreStack {
+    pub fn new(root: &Path, use_gitignore: bool) -> Self {
+        let absolute_root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+
+        let mut stack = Self {
+            use_gitignore,
+            root: root.to_path_buf(),
+            absolute_root: absolute_root.clone(),
+            files: Vec::new(),
+            pushed_counts: Vec::new(),
+        };
+
+        // Rules from above the base directory still apply inside of it, as long as they
+        // belong to the same repository.
+        let repository_root = find_repository_root(&absolute_root);
+        let ancestors: Vec<&Path> = match repository_root {
+            Some(ref repository_root) => absolute_root
+                .ancestors()
+                .skip(1)
+                .take_while(|ancestor| ancestor.starts_with(repository_root))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if use_gitignore {
+            let base = repository_root.as_deref().unwrap_or(&absolute_root);
+
+            if let Some(ignore_file) =
+                global_excludes_file().and_then(|file| IgnoreFile::read(base, &file))
+            {
+                stack.files.push(ignore_file);
+            }
+
+            if let Some(ignore_file) =
+                IgnoreFile::read(base, &base.join(".git").join("info").join("exclude"))
+            {
+                stack.files.push(ignore_file);
+            }
+        }
+
+        for ancestor in ancestors.into_iter().rev() {
+            stack.push_ignore_files(ancestor);
+        }
+
+        stack
+    }
+
+    /// Reads the ignore files of a directory the walk is about to list.
+    pub fn enter_directory(&mut self, directory: &Path) {
+        let directory = self.to_absolute(directory);
+        let pushed_count = self.push_ignore_files(&directory);
+        self.pushed_counts.push(pushed_count);
+    }
+
+    /// Forgets the ignore files of the directory the walk just finished.
+    pub fn leave_directory(&mut self) {
+        let pushed_count = self
+            .pushed_counts
+            .pop()
+            .expect("Every directory left should have been entered.");
+        self.files.truncate(self.files.len() - pushed_count);
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.use_gitignore && is_dir && path.file_name().is_some_and(|name| name == ".git") {
+            return true;
+        }
+
+        let path = self.to_absolute(path);
+
+        self.files
+            .iter()
+            .rev()
+            .find_map(|ignore_file| ignore_file.check(&path, is_dir))
+            .unwrap_or(false)
+    }
+
+    fn push_ignore_files(&mut self, directory: &Path) -> usize {
+        if !self.use_gitignore {
+            return 0;
+        }
+
+        let mut pushed_count = 0;
+        for file_name in IGNORE_FILES {
+            if let Some(ignore_file) = IgnoreFile::read(directory, &directory.join(file_name)) {
+                self.files.push(ignore_file);
+                pushed_count += 1;
+            }
+        }
+
+        pushed_count
+    }
+
+    /// Paths in the walk start with the base directory as it was typed, the rules use absolute paths.
+    fn to_absolute(&self, path: &Path) -> PathBuf {
+        match path.strip_prefix(&self.root) {
+            Ok(relative_path) => self.absolute_root.join(relative_path),
+            Err(_) => path.to_path_buf(),
+        }
+    }
+}
+
+fn find_repository_root(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// Finds the file set with `core.excludesFile`, falling back to the default `git/ignore`
+/// in the XDG config directory.
+fn global_excludes_file() -> Option<PathBuf> {
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(PathBuf::from);
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".config")));
+
+    let git_config_files = [
+        home.as_ref().map(|home| home.join(".gitconfig")),
+        config_home
+            .as_ref()
+            .map(|config_home| config_home.join("git").join("config")),
+    ];
+
+    for git_config_file in git_config_files.into_iter().flatten() {
+        if let Some(excludes_file) = read_excludes_file_setting(&git_config_file) {
+            return Some(match (excludes_file.strip_prefix("~/"), &home) {
+                (Some(rest), Some(home)) => home.join(rest),
+                _ => PathBuf::from(excludes_file),
+            });
+        }
+    }
+
+    config_home.map(|config_home| config_home.join("git").join("ignore"))
+}
+
+/// Reads `excludesFile` from the `[core]` section of a git config file.
+fn read_excludes_file_setting(git_config_file: &Path) -> Option<String> {
+    let contents = fs::read_to_string(git_config_file).ok()?;
+    let mut in_core_section = false;
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            in_core_section = line.eq_ignore_ascii_case("[core]");
+            continue;
+        }
+
+        if !in_core_section {
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once('=') {
+            if key.trim().eq_ignore_ascii_case("excludesfile") {
+                return Some(value.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_ignored(contents: &str, relative_path: &str, is_dir: bool) -> bool {
+        let ignore_file = IgnoreFile::parse(PathBuf::from("/repo"), contents);
+        ignore_file
+            .check(&Path::new("/repo").join(relative_path), is_dir)
+            .unwrap_or(false)
+    }
+
+    #[test]
+    fn test_ignore_file_unanchored() {
+        assert!(is_ignored("*.o", "main.o", false));
+        assert!(is_ignored("*.o", "src/deep/main.o", false));
+        assert!(!is_ignored("*.o", "main.rs", false));
+        assert!(!is_ignored("# *.o", "main.o", false));
+    }
+
+    #[test]
+    fn test_ignore_file_anchored() {
+        assert!(is_ignored("/target", "target", true));
+        assert!(!is_ignored("/target", "crates/target", true));
+        assert!(is_ignored("doc/frotz", "doc/frotz", true));
+        assert!(!is_ignored("doc/frotz", "a/doc/frotz", true));
+        assert!(is_ignored("**/build", "a/b/build", true));
+    }
+
+    #[test]
+    fn test_ignore_file_dir_only() {
+        assert!(is_ignored("logs/", "logs", true));
+        assert!(is_ignored("logs/", "app/logs", true));
+        assert!(!is_ignored("logs/", "logs", false));
+    }
+
+    #[test]
+    fn test_ignore_file_negation() {
+        let contents = "*.log\n!keep.log\n";
+        assert!(is_ignored(contents, "debug.log", false));
+        assert!(!is_ignored(contents, "keep.log", false));
+        assert!(is_ignored("!keep.log\n*.log\n", "keep.log", false));
+    }
+
+    #[test]
+    fn test_ignore_file_escapes() {
+        assert!(is_ignored("\\#notes", "#notes", false));
+        assert!(is_ignored("\\!important", "!important", false));
+        assert!(is_ignored("trailing   ", "trailing", false));
+        assert!(is_ignored("a|b", "a|b", false));
+    }
+
+    #[test]
+    fn test_ignore_file_outside_base() {
+        let ignore_file = IgnoreFile::parse(PathBuf::from("/repo/sub"), "*.o");
+        assert_eq!(ignore_file.check(Path::new("/repo/main.o"), false), None);
+        assert_eq!(
+            ignore_file.check(Path::new("/repo/sub/main.o"), false),
+            Some(true)
+        );
+    }
+}
@@ -1,15 +1,34 @@
+mod columns;
 mod config;
+mod config_error;
+mod config_file;
+mod dir_hash;
+mod du;
+mod filter;
+mod glob;
+mod hash;
+mod ignore;
+mod output;
+mod quote;
 mod render;
 mod run;
+mod size;
+mod sort;
+mod spec;
+mod update_file;
+mod users;
 mod utils;
+mod verify;
+mod walk_error;
 
 use config::Config;
 use run::run;
 use std::env::args;
+use std::process::ExitCode;
 
-fn main() {
+fn main() -> ExitCode {
     let args: Vec<String> = args().collect::<Vec<String>>();
-    let config = Config::from(args);
+    let config = Config::load(args);
 
-    run(config);
+    run(config)
 }
@@ -0,0 +1,325 @@
+// The renderer walks the directory and describes what it finds to an `Output`,
+// which decides what that looks like. Every format sees the exact same walk.
+mod diagram;
+mod html;
+mod json;
+mod markdown;
+mod ndjson;
+mod text;
+mod tree;
+mod xml;
+
+use super::hash::HashStats;
+use super::utils::{format_count, format_count_of, pluralize};
+use super::walk_error::{ErrorTally, WalkError};
+use diagram::DiagramStyle;
+use std::fs::FileType;
+use std::io::{self, Write};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum OutputFormat {
+    /// The classic `├── name` tree.
+    Text,
+    /// A nested JSON document, see `output::json` for the schema.
+    Json,
+    /// One JSON object per line, written as the walk happens. See `output::ndjson`.
+    Ndjson,
+    /// The same XML document as `tree -X`.
+    Xml,
+    /// A standalone page with collapsible directories.
+    Html,
+    /// Markdown, either the text tree in a code block or a nested list.
+    Markdown,
+    /// A Graphviz `digraph`.
+    Dot,
+    /// A Mermaid `graph TD`.
+    Mermaid,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" | "tree" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            "xml" => Some(Self::Xml),
+            "html" => Some(Self::Html),
+            "markdown" | "md" => Some(Self::Markdown),
+            "dot" | "graphviz" => Some(Self::Dot),
+            "mermaid" => Some(Self::Mermaid),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MarkdownStyle {
+    /// The text tree inside a fenced code block.
+    Fence,
+    /// A nested bullet list.
+    List,
+}
+
+impl MarkdownStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fence" | "code" => Some(Self::Fence),
+            "list" => Some(Self::List),
+            _ => None,
+        }
+    }
+}
+
+/// Everything about how the walk is written, set from the command line.
+#[derive(Debug, PartialEq, Clone)]
+pub struct OutputOptions {
+    pub format: OutputFormat,
+    /// Turns every name into a link to this address followed by the path, only used for HTML.
+    pub base_href: Option<String>,
+    pub markdown_style: MarkdownStyle,
+}
+
+impl OutputOptions {
+    pub fn new() -> Self {
+        Self {
+            format: OutputFormat::Text,
+            base_href: None,
+            markdown_style: MarkdownStyle::Fence,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum EntryKind {
+    Directory,
+    File,
+    Symlink,
+    Other,
+}
+
+impl EntryKind {
+    pub fn from_file_type(file_type: Option<FileType>) -> Self {
+        match file_type {
+            Some(file_type) if file_type.is_dir() => Self::Directory,
+            Some(file_type) if file_type.is_file() => Self::File,
+            Some(file_type) if file_type.is_symlink() => Self::Symlink,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Directory => "directory",
+            Self::File => "file",
+            Self::Symlink => "symlink",
+            Self::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LinkState {
+    /// Points to something that exists, but isn't listed.
+    Valid,
+    /// Points to nothing.
+    Broken,
+    /// Points to a directory that is listed because of `-l`.
+    Followed,
+    /// Points to a directory that contains the link, so `-l` didn't list it again.
+    Recursive,
+}
+
+impl LinkState {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Valid => "valid",
+            Self::Broken => "broken",
+            Self::Followed => "followed",
+            Self::Recursive => "recursive",
+        }
+    }
+}
+
+/// Where a symlink points, as it is written in the link.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Link<'a> {
+    pub target: &'a str,
+    /// The target quoted with `--quote`.
+    pub display_target: &'a str,
+    pub state: LinkState,
+}
+
+/// A single file or directory the renderer decided to show, or the base directory.
+pub struct Entry<'a> {
+    /// The name as it is, except for bytes that aren't UTF-8, for the formats read by programs.
+    pub name: &'a str,
+    /// The name quoted with `--quote`, for the formats read by people.
+    pub display_name: &'a str,
+    /// The path relative to the base directory, separated by `/`.
+    pub path: &'a str,
+    pub kind: EntryKind,
+    /// How many directories deep the entry is, the entries of the base directory are at 0.
+    pub depth: usize,
+    pub is_last_in_dir: bool,
+    /// A directory whose entries won't be listed because of the `-D` limit.
+    pub is_at_depth_limit: bool,
+    /// Only for symlinks.
+    pub link: Option<Link<'a>>,
+    /// `sha256:<hex>` for regular files with `--hash` and for directories with `--dir-hash`.
+    pub digest: Option<&'a str>,
+    /// The size of a file, or of a directory and everything inside of it, with `-s`.
+    pub size: Option<u64>,
+    /// What is shown before the branch glyphs in the text tree, empty without any columns.
+    pub columns: &'a str,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum HiddenReason {
+    /// The rest of the entries didn't fit in the `-L` limit.
+    DirLimit,
+    /// None of the entries are listed because of `-D 0`.
+    Depth,
+}
+
+/// The entries of a directory that were counted, but not shown.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct HiddenEntries {
+    pub dirs: usize,
+    pub files: usize,
+    pub reason: HiddenReason,
+}
+
+impl HiddenEntries {
+    pub fn get_count(&self) -> usize {
+        self.dirs + self.files
+    }
+
+    /// `… 1,234 more entries (56 dirs, 1,178 files)`, without the `more` for `-D 0`.
+    pub fn get_message(&self) -> String {
+        let qualifier = match self.reason {
+            HiddenReason::DirLimit => "more ",
+            HiddenReason::Depth => "",
+        };
+
+        format!(
+            "… {} {}{} ({}, {})",
+            format_count(self.get_count()),
+            qualifier,
+            pluralize(self.get_count(), "entry", "entries"),
+            format_count_of(self.dirs, "dir", "dirs"),
+            format_count_of(self.files, "file", "files"),
+        )
+    }
+}
+
+/// The statistics shown in verbose mode.
+pub struct Report {
+    /// How many base directories were shown.
+    pub roots: usize,
+    /// Every line the renderer produced, including unreadable directories.
+    pub displayed: usize,
+    pub dirs: usize,
+    pub files: usize,
+    pub hidden_dirs: usize,
+    pub hidden_files: usize,
+    pub elapsed_seconds: f32,
+    /// Only with `--hash`.
+    pub hash_stats: Option<HashStats>,
+    pub errors: ErrorTally,
+}
+
+impl Report {
+    pub fn get_message(&self) -> String {
+        let mut message = if self.roots > 1 {
+            format!(
+                "{} files and directories displayed from {} base directories in {:.2} seconds",
+                self.displayed, self.roots, self.elapsed_seconds
+            )
+        } else {
+            format!(
+                "{} files and directories displayed in {:.2} seconds",
+                self.displayed, self.elapsed_seconds
+            )
+        };
+
+        let hidden = self.hidden_dirs + self.hidden_files;
+        if hidden > 0 {
+            message.push_str(&format!(
+                "\n{} more hidden by the directory limit ({}, {})",
+                format_count(hidden),
+                format_count_of(self.hidden_dirs, "dir", "dirs"),
+                format_count_of(self.hidden_files, "file", "files"),
+            ));
+        }
+
+        if let Some(hash_stats) = self.hash_stats {
+            message.push('\n');
+            message.push_str(&hash_stats.get_message());
+        }
+
+        if self.errors.get_count() > 0 {
+            message.push('\n');
+            message.push_str(&self.errors.get_message());
+        }
+
+        message
+    }
+}
+
+/// Receives the walk of the renderer, in order.
+///
+/// Every `enter_directory` follows the `entry` of that directory and is matched by a
+/// `leave_directory` once all of its entries are done.
+pub trait Output {
+    /// The base directory, named as it was typed, with the path `.` and depth 0. Given again
+    /// for every further base directory, once the one before is done.
+    fn begin(&mut self, root: &Entry) -> io::Result<()>;
+
+    fn entry(&mut self, entry: &Entry) -> io::Result<()>;
+
+    fn enter_directory(&mut self) -> io::Result<()>;
+
+    fn leave_directory(&mut self) -> io::Result<()>;
+
+    /// The directory being listed couldn't be read, nothing else is coming for it.
+    fn unreadable(&mut self, error: WalkError, depth: usize) -> io::Result<()>;
+
+    /// Always the last thing in the directory being listed.
+    fn hidden_entries(&mut self, hidden: &HiddenEntries, depth: usize) -> io::Result<()>;
+
+    /// The `-T` limit cut the walk short, nothing else is coming.
+    fn total_limit_reached(&mut self) -> io::Result<()>;
+
+    /// The report is only given in verbose mode.
+    fn finish(&mut self, report: Option<&Report>) -> io::Result<()>;
+}
+
+pub fn new_output(options: OutputOptions, writer: Box<dyn Write>) -> Box<dyn Output> {
+    match options.format {
+        OutputFormat::Text => Box::new(text::TextOutput::new(writer)),
+        OutputFormat::Markdown => match options.markdown_style {
+            MarkdownStyle::Fence => Box::new(text::TextOutput::new_fenced(writer)),
+            MarkdownStyle::List => Box::new(tree::TreeOutput::new(
+                writer,
+                Box::new(markdown::write_markdown_list),
+            )),
+        },
+        OutputFormat::Ndjson => Box::new(ndjson::NdjsonOutput::new(writer)),
+        OutputFormat::Json => Box::new(tree::TreeOutput::new(writer, Box::new(json::write_json))),
+        OutputFormat::Xml => Box::new(tree::TreeOutput::new(writer, Box::new(xml::write_xml))),
+        OutputFormat::Dot => Box::new(tree::TreeOutput::new(
+            writer,
+            Box::new(|writer, tree| diagram::write_diagram(writer, tree, DiagramStyle::Dot)),
+        )),
+        OutputFormat::Mermaid => Box::new(tree::TreeOutput::new(
+            writer,
+            Box::new(|writer, tree| diagram::write_diagram(writer, tree, DiagramStyle::Mermaid)),
+        )),
+        OutputFormat::Html => Box::new(tree::TreeOutput::new(
+            writer,
+            Box::new(move |writer, tree| {
+                html::write_html(writer, tree, options.base_href.as_deref())
+            }),
+        )),
+    }
+}
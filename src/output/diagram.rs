@@ -0,0 +1,184 @@
+//! `--output dot` and `--output mermaid` draw the walk as a graph, with an edge from every
+//! directory to each of its entries. Directories whose entries were left out by `-D`, `-L`
+//! or `-T` get a dashed placeholder node standing in for what is missing.
+use super::tree::{Node, Tree, Truncation};
+use std::io::{self, Write};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DiagramStyle {
+    Dot,
+    Mermaid,
+}
+
+enum NodeClass {
+    Directory,
+    File,
+    Elided,
+}
+
+/// Writes the nodes and edges of the graph, numbering the nodes as they come.
+struct DiagramWriter<'a> {
+    writer: &'a mut dyn Write,
+    style: DiagramStyle,
+    next_id: usize,
+}
+
+impl DiagramWriter<'_> {
+    fn write_node(&mut self, label: &str, class: NodeClass) -> io::Result<String> {
+        let id = format!("n{}", self.next_id);
+        self.next_id += 1;
+
+        match self.style {
+            DiagramStyle::Dot => {
+                let attributes = match class {
+                    NodeClass::Directory => "shape=folder, style=filled, fillcolor=\"#dbe9f6\"",
+                    NodeClass::File => "shape=note",
+                    NodeClass::Elided => "shape=box, style=dashed, fontcolor=\"#777777\"",
+                };
+                writeln!(
+                    self.writer,
+                    "  {id} [label={}, {attributes}];",
+                    escape_dot(label)
+                )?;
+            }
+            DiagramStyle::Mermaid => {
+                let class = match class {
+                    NodeClass::Directory => "directory",
+                    NodeClass::File => "file",
+                    NodeClass::Elided => "elided",
+                };
+                writeln!(
+                    self.writer,
+                    "  {id}[\"{}\"]:::{class}",
+                    escape_mermaid(label)
+                )?;
+            }
+        }
+
+        Ok(id)
+    }
+
+    fn write_edge(&mut self, from: &str, to: &str) -> io::Result<()> {
+        match self.style {
+            DiagramStyle::Dot => writeln!(self.writer, "  {from} -> {to};"),
+            DiagramStyle::Mermaid => writeln!(self.writer, "  {from} --> {to}"),
+        }
+    }
+
+    fn write_tree(&mut self, node: &Node) -> io::Result<String> {
+        if !node.is_directory() {
+            return self.write_node(&node.display_name, NodeClass::File);
+        }
+
+        let id = self.write_node(&format!("{}/", node.display_name), NodeClass::Directory)?;
+
+        if let Some(error) = node.error {
+            let label = format!("[{}]", error.get_message());
+            let restricted_id = self.write_node(&label, NodeClass::Elided)?;
+            self.write_edge(&id, &restricted_id)?;
+        }
+
+        for child in &node.children {
+            let child_id = self.write_tree(child)?;
+            self.write_edge(&id, &child_id)?;
+        }
+
+        let elided_label = match node.truncation {
+            None => None,
+            Some(Truncation::Depth) | Some(Truncation::TotalLimit) => Some("…".to_string()),
+            Some(Truncation::Hidden(hidden)) => Some(hidden.get_message()),
+        };
+
+        if let Some(elided_label) = elided_label {
+            let elided_id = self.write_node(&elided_label, NodeClass::Elided)?;
+            self.write_edge(&id, &elided_id)?;
+        }
+
+        Ok(id)
+    }
+}
+
+pub fn write_diagram(writer: &mut dyn Write, tree: &Tree, style: DiagramStyle) -> io::Result<()> {
+    match style {
+        DiagramStyle::Dot => {
+            writeln!(writer, "digraph mtree {{")?;
+            writeln!(writer, "  rankdir=LR;")?;
+            writeln!(writer, "  node [fontname=\"Helvetica\"];")?;
+        }
+        DiagramStyle::Mermaid => writeln!(writer, "graph TD")?,
+    }
+
+    let mut diagram_writer = DiagramWriter {
+        writer,
+        style,
+        next_id: 0,
+    };
+    for root in tree.roots {
+        diagram_writer.write_tree(root)?;
+    }
+
+    match style {
+        DiagramStyle::Dot => writeln!(writer, "}}"),
+        DiagramStyle::Mermaid => {
+            writeln!(writer, "  classDef directory fill:#dbe9f6,stroke:#4a86c5")?;
+            writeln!(writer, "  classDef file fill:#ffffff,stroke:#999999")?;
+            writeln!(
+                writer,
+                "  classDef elided fill:#f4f4f4,stroke:#bbbbbb,stroke-dasharray:4 4,color:#777777"
+            )
+        }
+    }
+}
+
+/// Quotes a DOT string, escaping `"` and `\`.
+fn escape_dot(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+/// Mermaid labels can't contain `"` and treat `<`, `>` as HTML, so those become entity codes.
+fn escape_mermaid(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("#quot;"),
+            '<' => escaped.push_str("#lt;"),
+            '>' => escaped.push_str("#gt;"),
+            '#' => escaped.push_str("#35;"),
+            '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_dot() {
+        assert_eq!(escape_dot("main.rs"), "\"main.rs\"");
+        assert_eq!(escape_dot("a\"b\\c"), "\"a\\\"b\\\\c\"");
+    }
+
+    #[test]
+    fn test_escape_mermaid() {
+        assert_eq!(escape_mermaid("main.rs"), "main.rs");
+        assert_eq!(escape_mermaid("\"<#>\""), "#quot;#lt;#35;#gt;#quot;");
+    }
+}
@@ -0,0 +1,152 @@
+//! `--output html` writes a standalone page where every directory can be folded with
+//! `<details>`, without any scripts or outside style sheets.
+use super::tree::{Node, Tree, Truncation};
+use super::xml::escape_xml;
+use crate::utils::format_count_of;
+use std::io::{self, Write};
+
+const STYLE: &str = "body { font-family: sans-serif; }
+ul.mtree, ul.mtree ul { list-style: none; padding-left: 1.25em; border-left: 1px dotted #999; }
+ul.mtree { border-left: none; padding-left: 0; }
+ul.mtree summary { cursor: pointer; font-weight: bold; }
+ul.mtree .note { color: #777; font-style: italic; }
+footer { margin-top: 1em; color: #555; white-space: pre-line; }";
+
+/// How many levels of directories start out unfolded.
+const OPEN_DEPTH: usize = 1;
+
+pub fn write_html(writer: &mut dyn Write, tree: &Tree, base_href: Option<&str>) -> io::Result<()> {
+    let names: Vec<&str> = tree
+        .roots
+        .iter()
+        .map(|root| root.display_name.as_str())
+        .collect();
+    let title = escape_xml(&names.join(", "));
+
+    writeln!(writer, "<!DOCTYPE html>")?;
+    writeln!(writer, "<html lang=\"en\">")?;
+    writeln!(writer, "<head>")?;
+    writeln!(writer, "<meta charset=\"utf-8\">")?;
+    writeln!(writer, "<title>{title}</title>")?;
+    writeln!(writer, "<style>\n{STYLE}\n</style>")?;
+    writeln!(writer, "</head>")?;
+    writeln!(writer, "<body>")?;
+    for (index, root) in tree.roots.iter().enumerate() {
+        writeln!(writer, "<h1>{}</h1>", get_label(root, base_href))?;
+
+        writeln!(writer, "<ul class=\"mtree\">")?;
+        write_children(writer, root, 0, base_href)?;
+        // The walk stops at the limit, so only the last base directory can be cut short by it.
+        if tree.is_total_limit_reached && index + 1 == tree.roots.len() {
+            writeln!(
+                writer,
+                "<li class=\"note\">… the total limit was reached</li>"
+            )?;
+        }
+        writeln!(writer, "</ul>")?;
+    }
+
+    let (dirs, files) = tree.count();
+    write!(
+        writer,
+        "<footer>{}, {}",
+        format_count_of(dirs, "directory", "directories"),
+        format_count_of(files, "file", "files")
+    )?;
+    if let Some(report) = tree.report {
+        write!(writer, "\n{}", escape_xml(&report.get_message()))?;
+    }
+    writeln!(writer, "</footer>")?;
+
+    writeln!(writer, "</body>")?;
+    writeln!(writer, "</html>")
+}
+
+fn write_children(
+    writer: &mut dyn Write,
+    node: &Node,
+    depth: usize,
+    base_href: Option<&str>,
+) -> io::Result<()> {
+    if let Some(error) = node.error {
+        writeln!(writer, "<li class=\"note\">[{}]</li>", error.get_message())?;
+    }
+
+    for child in &node.children {
+        write_node(writer, child, depth, base_href)?;
+    }
+
+    match node.truncation {
+        Some(Truncation::Hidden(hidden)) => {
+            writeln!(writer, "<li class=\"note\">{}</li>", hidden.get_message())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn write_node(
+    writer: &mut dyn Write,
+    node: &Node,
+    depth: usize,
+    base_href: Option<&str>,
+) -> io::Result<()> {
+    let label = get_label(node, base_href);
+
+    if !node.is_directory() {
+        return writeln!(writer, "<li>{label}</li>");
+    }
+
+    if node.truncation == Some(Truncation::Depth) {
+        return writeln!(writer, "<li>{label}/ <span class=\"note\">…</span></li>");
+    }
+
+    let open = if depth < OPEN_DEPTH { " open" } else { "" };
+    writeln!(writer, "<li><details{open}><summary>{label}/</summary><ul>")?;
+    write_children(writer, node, depth + 1, base_href)?;
+    writeln!(writer, "</ul></details></li>")
+}
+
+/// The escaped name, linked to `base_href` followed by the path when there is one.
+fn get_label(node: &Node, base_href: Option<&str>) -> String {
+    let name = escape_xml(&node.display_name);
+
+    match base_href {
+        Some(base_href) => {
+            let mut href = base_href.trim_end_matches('/').to_string();
+            if node.path != "." {
+                href.push('/');
+                href.push_str(&encode_path(&node.path));
+            }
+            format!("<a href=\"{}\">{name}</a>", escape_xml(&href))
+        }
+        None => name,
+    }
+}
+
+/// Percent encodes a `/` separated path for use in a link, leaving the separators alone.
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_path() {
+        assert_eq!(encode_path("src/main.rs"), "src/main.rs");
+        assert_eq!(encode_path("my docs/a#b?.md"), "my%20docs/a%23b%3F.md");
+        assert_eq!(encode_path("ü"), "%C3%BC");
+    }
+}
@@ -0,0 +1,234 @@
+//! `--output json` writes the walk as a single JSON document.
+//!
+//! The schema is versioned, fields are only ever added within a version:
+//!
+//! ```text
+//! {
+//!   "schema": "mtree-tree",
+//!   "version": 1,
+//!   "root": <node>,                  // the first base directory
+//!   "roots": [<node>, ...],          // only with more than one, every base directory in order,
+//!                                    // starting with the same node as "root"
+//!   "total_limit_reached": <bool>,   // true when -T cut the walk short
+//!   "report": <report>               // only with -v
+//! }
+//!
+//! <node> = {
+//!   "name": <string>,                // the base directory as typed for the root
+//!   "type": "directory" | "file" | "symlink" | "other",
+//!   "path": <string>,                // relative to the base directory, "/" separated, "." for the root
+//!   "digest": <string>,              // "sha256:<hex>", files with --hash, directories with --dir-hash
+//!   "size": <int>,                   // bytes with -s, directories count everything inside of them
+//!   "target": <string>,              // symlinks only, where the link points
+//!   "link": "valid" | "broken" | "followed" | "recursive",  // symlinks only
+//!   // directories and links followed with -l only:
+//!   "children": [<node>, ...],
+//!   "restricted": <bool>,            // the directory couldn't be read
+//!   "error": null | <error>,         // why it couldn't be read
+//!   "truncated": null | {
+//!     "reason": "depth" | "dir_limit" | "total_limit",
+//!     "hidden": { "entries": <int>, "directories": <int>, "files": <int> }  // when counted
+//!   }
+//! }
+//!
+//! <report> = {
+//!   "roots": <int>,                  // how many base directories were shown
+//!   "displayed": <int>,
+//!   "directories": <int>,
+//!   "files": <int>,
+//!   "hidden": { "entries": <int>, "directories": <int>, "files": <int> },
+//!   "elapsed_seconds": <number>,
+//!   "hashed": { "files": <int>, "bytes": <int>, "seconds": <number> },  // only with --hash
+//!   "errors": { "permission_denied": <int>, "not_found": <int>, "not_a_directory": <int>, "io": <int> }
+//! }
+//!
+//! <error> = "permission_denied" | "not_found" | "not_a_directory" | "io"
+//! ```
+use super::tree::{Node, Tree, Truncation};
+use super::{HiddenEntries, HiddenReason, Report};
+use std::io::{self, Write};
+
+pub const SCHEMA_NAME: &str = "mtree-tree";
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub fn write_json(writer: &mut dyn Write, tree: &Tree) -> io::Result<()> {
+    writeln!(writer, "{{")?;
+    writeln!(writer, "  \"schema\": {},", escape_json(SCHEMA_NAME))?;
+    writeln!(writer, "  \"version\": {SCHEMA_VERSION},")?;
+    // Several base directories are an addition, readers of a single one still find it in "root".
+    write!(writer, "  \"root\": ")?;
+    write_node(writer, &tree.roots[0], 1)?;
+    if tree.roots.len() > 1 {
+        writeln!(writer, ",")?;
+        write!(writer, "  \"roots\": [")?;
+        for (index, root) in tree.roots.iter().enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "\n    ")?;
+            write_node(writer, root, 2)?;
+        }
+        write!(writer, "\n  ]")?;
+    }
+    writeln!(writer, ",")?;
+    write!(
+        writer,
+        "  \"total_limit_reached\": {}",
+        tree.is_total_limit_reached
+    )?;
+
+    if let Some(report) = tree.report {
+        writeln!(writer, ",")?;
+        write!(writer, "  \"report\": {}", get_report_json(report))?;
+    }
+
+    writeln!(writer, "\n}}")
+}
+
+fn write_node(writer: &mut dyn Write, node: &Node, depth: usize) -> io::Result<()> {
+    let indent = "  ".repeat(depth + 1);
+
+    writeln!(writer, "{{")?;
+    write!(
+        writer,
+        "{indent}\"name\": {},\n{indent}\"type\": {},\n{indent}\"path\": {}",
+        escape_json(&node.name),
+        escape_json(node.kind.get_name()),
+        escape_json(&node.path),
+    )?;
+
+    if let Some(ref digest) = node.digest {
+        write!(writer, ",\n{indent}\"digest\": {}", escape_json(digest))?;
+    }
+
+    if let Some(size) = node.size {
+        write!(writer, ",\n{indent}\"size\": {size}")?;
+    }
+
+    if let Some((ref target, state)) = node.link {
+        write!(
+            writer,
+            ",\n{indent}\"target\": {},\n{indent}\"link\": {}",
+            escape_json(target),
+            escape_json(state.get_name()),
+        )?;
+    }
+
+    if node.is_directory() {
+        write!(writer, ",\n{indent}\"children\": [")?;
+        for (index, child) in node.children.iter().enumerate() {
+            if index > 0 {
+                write!(writer, ",")?;
+            }
+            write!(writer, "\n{indent}  ")?;
+            write_node(writer, child, depth + 2)?;
+        }
+        if !node.children.is_empty() {
+            write!(writer, "\n{indent}")?;
+        }
+        write!(writer, "]")?;
+
+        write!(
+            writer,
+            ",\n{indent}\"restricted\": {},\n{indent}\"error\": {},\n{indent}\"truncated\": {}",
+            node.error.is_some(),
+            node.error.map_or("null".to_string(), |error| format!(
+                "\"{}\"",
+                error.get_name()
+            )),
+            get_truncation_json(node.truncation.as_ref()),
+        )?;
+    }
+
+    write!(writer, "\n{}}}", "  ".repeat(depth))
+}
+
+fn get_truncation_json(truncation: Option<&Truncation>) -> String {
+    match truncation {
+        None => "null".to_string(),
+        Some(Truncation::Depth) => "{\"reason\": \"depth\"}".to_string(),
+        Some(Truncation::TotalLimit) => "{\"reason\": \"total_limit\"}".to_string(),
+        Some(Truncation::Hidden(hidden)) => {
+            let reason = match hidden.reason {
+                HiddenReason::DirLimit => "dir_limit",
+                HiddenReason::Depth => "depth",
+            };
+            format!(
+                "{{\"reason\": \"{reason}\", \"hidden\": {}}}",
+                get_hidden_json(hidden)
+            )
+        }
+    }
+}
+
+fn get_hidden_json(hidden: &HiddenEntries) -> String {
+    format!(
+        "{{\"entries\": {}, \"directories\": {}, \"files\": {}}}",
+        hidden.get_count(),
+        hidden.dirs,
+        hidden.files
+    )
+}
+
+pub fn get_report_json(report: &Report) -> String {
+    let hashed = report.hash_stats.map_or(String::new(), |hash_stats| {
+        format!(
+            ", \"hashed\": {{\"files\": {}, \"bytes\": {}, \"seconds\": {}}}",
+            hash_stats.files,
+            hash_stats.bytes,
+            hash_stats.elapsed.as_secs_f32()
+        )
+    });
+
+    let errors = &report.errors;
+
+    format!(
+        "{{\"roots\": {}, \"displayed\": {}, \"directories\": {}, \"files\": {}, \"hidden\": {{\"entries\": {}, \"directories\": {}, \"files\": {}}}, \"elapsed_seconds\": {}{hashed}, \"errors\": {{\"permission_denied\": {}, \"not_found\": {}, \"not_a_directory\": {}, \"io\": {}}}}}",
+        report.roots,
+        report.displayed,
+        report.dirs,
+        report.files,
+        report.hidden_dirs + report.hidden_files,
+        report.hidden_dirs,
+        report.hidden_files,
+        report.elapsed_seconds,
+        errors.permission_denied,
+        errors.not_found,
+        errors.not_a_directory,
+        errors.io,
+    )
+}
+
+/// Quotes and escapes a string for use in JSON.
+pub fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_json() {
+        assert_eq!(escape_json("plain"), "\"plain\"");
+        assert_eq!(escape_json("say \"hi\""), "\"say \\\"hi\\\"\"");
+        assert_eq!(escape_json("C:\\Windows"), "\"C:\\\\Windows\"");
+        assert_eq!(escape_json("a\nb\u{1b}"), "\"a\\nb\\u001b\"");
+    }
+}
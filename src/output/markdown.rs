@@ -0,0 +1,80 @@
+//! `--output markdown --markdown-style list` writes the walk as a nested bullet list.
+//! The default `fence` style is the text tree in a code block, see `TextOutput::new_fenced`.
+use super::tree::{Node, Tree, Truncation};
+use std::io::{self, Write};
+
+pub fn write_markdown_list(writer: &mut dyn Write, tree: &Tree) -> io::Result<()> {
+    for root in tree.roots {
+        writeln!(writer, "- {}/", escape_markdown(&root.display_name))?;
+        write_children(writer, root, 1)?;
+    }
+
+    if tree.is_total_limit_reached {
+        writeln!(writer, "- …")?;
+    }
+
+    if let Some(report) = tree.report {
+        writeln!(writer)?;
+        for line in report.get_message().lines() {
+            writeln!(writer, "{line}  ")?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_children(writer: &mut dyn Write, node: &Node, depth: usize) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+
+    if let Some(error) = node.error {
+        writeln!(writer, "{indent}- \\[{}\\]", error.get_message())?;
+    }
+
+    for child in &node.children {
+        let name = escape_markdown(&child.display_name);
+
+        if !child.is_directory() {
+            writeln!(writer, "{indent}- {name}")?;
+        } else if child.truncation == Some(Truncation::Depth) {
+            writeln!(writer, "{indent}- {name}/ …")?;
+        } else {
+            writeln!(writer, "{indent}- {name}/")?;
+            write_children(writer, child, depth + 1)?;
+        }
+    }
+
+    if let Some(Truncation::Hidden(hidden)) = node.truncation {
+        writeln!(writer, "{indent}- {}", hidden.get_message())?;
+    }
+
+    Ok(())
+}
+
+/// Escapes the characters that would turn a file name into emphasis, links or HTML.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|' | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_markdown() {
+        assert_eq!(escape_markdown("main.rs"), "main.rs");
+        assert_eq!(escape_markdown("__init__.py"), "\\_\\_init\\_\\_.py");
+        assert_eq!(escape_markdown("[draft] <b>"), "\\[draft\\] \\<b\\>");
+    }
+}
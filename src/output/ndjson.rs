@@ -0,0 +1,157 @@
+//! `--output ndjson` writes one JSON object per line as the walk happens, so huge trees
+//! can be consumed while they are being walked, without holding them in memory.
+//!
+//! Every line has a `record` field saying what kind of line it is:
+//!
+//! ```text
+//! {"record": "entry", "index": <int>, "parent": <int> | null, "name": <string>, "path": <string>,
+//!  "depth": <int>, "type": <type>, "is_last": <bool>, "at_depth_limit": <bool>,
+//!  "digest": <string>,   // files with --hash, directories with --dir-hash
+//!  "size": <int>,        // with -s, directories count everything inside of them
+//!  "target": <string>, "link": <link>}   // symlinks only, the same as for `--output json`
+//! {"record": "restricted", "parent": <int>, "error": <error>}   // the directory couldn't be read
+//! {"record": "hidden", "parent": <int>, "reason": "depth" | "dir_limit", "entries": <int>, "directories": <int>, "files": <int>}
+//! {"record": "total_limit_reached", "parent": <int>}
+//! {"record": "report", ...}   // only with -v, the same fields as the `report` of `--output json`
+//! ```
+//!
+//! The base directory is the first entry, with index 0, depth 0 and no parent, and every
+//! further base directory is another entry like it. Every other entry refers to the index
+//! of the directory it is in, and `path` is relative to its base directory. The `type` values are the same as for `--output json`.
+use super::json::{escape_json, get_report_json};
+use super::{Entry, HiddenEntries, HiddenReason, Output, Report, WalkError};
+use std::io::{self, Write};
+
+pub struct NdjsonOutput {
+    writer: Box<dyn Write>,
+    next_index: usize,
+    // The index of the base directory followed by the directories being listed, deepest last.
+    parent_indices: Vec<usize>,
+    // The index of the latest entry, becomes a parent when the walk enters it.
+    last_index: usize,
+}
+
+impl NdjsonOutput {
+    pub fn new(writer: Box<dyn Write>) -> Self {
+        Self {
+            writer,
+            next_index: 0,
+            parent_indices: Vec::new(),
+            last_index: 0,
+        }
+    }
+
+    fn get_parent_index(&self) -> usize {
+        *self
+            .parent_indices
+            .last()
+            .expect("The base directory should stay on the stack.")
+    }
+
+    fn write_entry(
+        &mut self,
+        parent: Option<usize>,
+        entry: &Entry,
+        depth: usize,
+    ) -> io::Result<()> {
+        let index = self.next_index;
+        self.next_index += 1;
+        self.last_index = index;
+
+        let digest = entry.digest.map_or(String::new(), |digest| {
+            format!(", \"digest\": {}", escape_json(digest))
+        });
+        let size = entry
+            .size
+            .map_or(String::new(), |size| format!(", \"size\": {size}"));
+        let link = entry.link.map_or(String::new(), |link| {
+            format!(
+                ", \"target\": {}, \"link\": {}",
+                escape_json(link.target),
+                escape_json(link.state.get_name())
+            )
+        });
+
+        writeln!(
+            self.writer,
+            "{{\"record\": \"entry\", \"index\": {index}, \"parent\": {}, \"name\": {}, \"path\": {}, \"depth\": {depth}, \"type\": {}, \"is_last\": {}, \"at_depth_limit\": {}{digest}{size}{link}}}",
+            parent.map_or("null".to_string(), |parent| parent.to_string()),
+            escape_json(entry.name),
+            escape_json(entry.path),
+            escape_json(entry.kind.get_name()),
+            entry.is_last_in_dir,
+            entry.is_at_depth_limit,
+        )
+    }
+}
+
+impl Output for NdjsonOutput {
+    fn begin(&mut self, root: &Entry) -> io::Result<()> {
+        self.parent_indices.clear();
+        self.write_entry(None, root, 0)?;
+        self.parent_indices.push(self.last_index);
+        Ok(())
+    }
+
+    fn entry(&mut self, entry: &Entry) -> io::Result<()> {
+        // The base directory takes depth 0, so everything in it starts at 1.
+        self.write_entry(Some(self.get_parent_index()), entry, entry.depth + 1)
+    }
+
+    fn enter_directory(&mut self) -> io::Result<()> {
+        self.parent_indices.push(self.last_index);
+        Ok(())
+    }
+
+    fn leave_directory(&mut self) -> io::Result<()> {
+        self.parent_indices.pop();
+        Ok(())
+    }
+
+    fn unreadable(&mut self, error: WalkError, _depth: usize) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "{{\"record\": \"restricted\", \"parent\": {}, \"error\": \"{}\"}}",
+            self.get_parent_index(),
+            error.get_name()
+        )
+    }
+
+    fn hidden_entries(&mut self, hidden: &HiddenEntries, _depth: usize) -> io::Result<()> {
+        let reason = match hidden.reason {
+            HiddenReason::DirLimit => "dir_limit",
+            HiddenReason::Depth => "depth",
+        };
+
+        writeln!(
+            self.writer,
+            "{{\"record\": \"hidden\", \"parent\": {}, \"reason\": \"{reason}\", \"entries\": {}, \"directories\": {}, \"files\": {}}}",
+            self.get_parent_index(),
+            hidden.get_count(),
+            hidden.dirs,
+            hidden.files,
+        )
+    }
+
+    fn total_limit_reached(&mut self) -> io::Result<()> {
+        writeln!(
+            self.writer,
+            "{{\"record\": \"total_limit_reached\", \"parent\": {}}}",
+            self.get_parent_index()
+        )
+    }
+
+    fn finish(&mut self, report: Option<&Report>) -> io::Result<()> {
+        if let Some(report) = report {
+            let report_json = get_report_json(report);
+            // Splice the record type into the report object.
+            writeln!(
+                self.writer,
+                "{{\"record\": \"report\", {}",
+                report_json.trim_start_matches('{')
+            )?;
+        }
+
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,204 @@
+use super::{Entry, HiddenEntries, LinkState, Output, Report, WalkError};
+use crate::hash::shorten_digest;
+use std::io::{self, Write};
+
+/// Draws the tree with box drawing characters, one line per entry.
+pub struct TextOutput {
+    writer: Box<dyn Write>,
+    // Remembers the past to determine if we should draw:
+    // │   ├── file_name
+    // or
+    //     ├── file_name
+    // Grows as the tree gets deeper, since the depth can be unlimited.
+    draw_layer_table: Vec<bool>,
+    // Wraps the tree in a Markdown code block.
+    is_fenced: bool,
+    // The width of the columns, lines that aren't entries get as many blanks to line up.
+    columns_width: usize,
+    // Whether a base directory was shown already, the next one is set apart by a blank line.
+    has_begun: bool,
+    // How many directories deep the walk is, the layers past it are left over from earlier ones.
+    depth: usize,
+}
+
+impl TextOutput {
+    pub fn new(writer: Box<dyn Write>) -> Self {
+        Self {
+            writer,
+            draw_layer_table: Vec::new(),
+            is_fenced: false,
+            columns_width: 0,
+            has_begun: false,
+            depth: 0,
+        }
+    }
+
+    pub fn new_fenced(writer: Box<dyn Write>) -> Self {
+        Self {
+            is_fenced: true,
+            ..Self::new(writer)
+        }
+    }
+
+    /// The columns followed by a gap, or blanks as wide for lines without columns.
+    fn get_columns_prefix(&self, columns: Option<&str>) -> String {
+        if self.columns_width == 0 {
+            return String::new();
+        }
+
+        match columns {
+            Some(columns) => format!("{columns}  "),
+            None => " ".repeat(self.columns_width + 2),
+        }
+    }
+
+    /// Builds the `│   ├── ` part of a line for an entry at depth.
+    fn get_branch_prefix(&self, depth: usize, is_last_in_dir: bool) -> String {
+        let mut prefix: String = String::new();
+
+        for &render_layer in self.draw_layer_table.iter().take(depth) {
+            if render_layer {
+                prefix.push_str("│   ");
+            } else {
+                prefix.push_str("    ");
+            }
+        }
+
+        if is_last_in_dir {
+            prefix.push_str("└── ");
+        } else {
+            prefix.push_str("├── ");
+        }
+
+        prefix
+    }
+}
+
+impl Output for TextOutput {
+    fn begin(&mut self, root: &Entry) -> io::Result<()> {
+        if self.has_begun {
+            writeln!(self.writer)?;
+        } else if self.is_fenced {
+            writeln!(self.writer, "```text")?;
+        }
+        self.has_begun = true;
+        self.depth = 0;
+
+        self.columns_width = root.columns.chars().count();
+
+        let mut print_buffer = self.get_columns_prefix(Some(root.columns));
+        print_buffer.push_str(root.display_name);
+
+        if let Some(digest) = root.digest {
+            print_buffer.push_str("  ");
+            print_buffer.push_str(&shorten_digest(digest));
+        }
+
+        writeln!(self.writer, "{print_buffer}")
+    }
+
+    fn entry(&mut self, entry: &Entry) -> io::Result<()> {
+        if entry.depth == self.draw_layer_table.len() {
+            self.draw_layer_table.push(true);
+        }
+        self.draw_layer_table[entry.depth] = !entry.is_last_in_dir;
+
+        let mut print_buffer = self.get_columns_prefix(Some(entry.columns));
+        print_buffer.push_str(&self.get_branch_prefix(entry.depth, entry.is_last_in_dir));
+
+        print_buffer.push_str(entry.display_name);
+
+        if let Some(link) = entry.link {
+            print_buffer.push_str(" -> ");
+            print_buffer.push_str(link.display_target);
+
+            match link.state {
+                LinkState::Broken => print_buffer.push_str(" [broken link]"),
+                LinkState::Recursive => print_buffer.push_str(" [recursive, not followed]"),
+                LinkState::Valid | LinkState::Followed => (),
+            }
+        }
+
+        if entry.is_at_depth_limit {
+            print_buffer.push_str(" ...");
+        }
+
+        if let Some(digest) = entry.digest {
+            print_buffer.push_str("  ");
+            print_buffer.push_str(&shorten_digest(digest));
+        }
+
+        writeln!(self.writer, "{print_buffer}")
+    }
+
+    fn enter_directory(&mut self) -> io::Result<()> {
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave_directory(&mut self) -> io::Result<()> {
+        self.depth -= 1;
+        Ok(())
+    }
+
+    /// Prints `└── [permission denied]`.
+    fn unreadable(&mut self, error: WalkError, depth: usize) -> io::Result<()> {
+        let mut print_buffer = self.get_columns_prefix(None);
+        print_buffer.push_str(&self.get_branch_prefix(depth, true));
+        writeln!(self.writer, "{print_buffer}[{}]", error.get_message())
+    }
+
+    /// Prints `└── … 1,234 more entries (56 dirs, 1,178 files)`.
+    fn hidden_entries(&mut self, hidden: &HiddenEntries, depth: usize) -> io::Result<()> {
+        let mut print_buffer = self.get_columns_prefix(None);
+        print_buffer.push_str(&self.get_branch_prefix(depth, true));
+        print_buffer.push_str(&hidden.get_message());
+
+        writeln!(self.writer, "{print_buffer}")
+    }
+
+    /// Prints `... ...` under every directory that still had entries to come.
+    fn total_limit_reached(&mut self) -> io::Result<()> {
+        let mut print_buffer = self.get_columns_prefix(None);
+
+        for &render_layer in self.draw_layer_table.iter().take(self.depth + 1) {
+            if render_layer {
+                print_buffer.push_str("... ");
+            } else {
+                print_buffer.push_str("    ");
+            }
+        }
+
+        writeln!(self.writer, "{}", print_buffer.trim_end())
+    }
+
+    fn finish(&mut self, report: Option<&Report>) -> io::Result<()> {
+        if let Some(report) = report {
+            writeln!(self.writer, "\n{}", report.get_message())?;
+        }
+
+        if self.is_fenced {
+            writeln!(self.writer, "```")?;
+        }
+
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::SharedBuffer;
+
+    #[test]
+    fn test_total_limit_reached_leaves_out_deeper_layers() {
+        let buffer = SharedBuffer::new();
+        let mut output = TextOutput::new(Box::new(buffer.clone()));
+        // Left over from a deeper directory that was already finished.
+        output.draw_layer_table = vec![true, false, true];
+        output.depth = 1;
+
+        output.total_limit_reached().unwrap();
+        assert_eq!(buffer.into_string(), "...\n");
+    }
+}
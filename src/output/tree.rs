@@ -0,0 +1,184 @@
+use super::{Entry, EntryKind, HiddenEntries, LinkState, Output, Report, WalkError};
+use std::io::{self, Write};
+
+/// Why a directory isn't listed in full.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Truncation {
+    /// The directory is at the `-D` limit, so its entries were never read.
+    Depth,
+    /// Some or all of the entries were counted, but not listed.
+    Hidden(HiddenEntries),
+    /// The `-T` limit was reached while listing this directory.
+    TotalLimit,
+}
+
+/// An entry together with everything listed inside of it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Node {
+    pub name: String,
+    /// The name quoted with `--quote`, for the formats read by people.
+    pub display_name: String,
+    pub path: String,
+    pub kind: EntryKind,
+    pub children: Vec<Node>,
+    pub truncation: Option<Truncation>,
+    /// Why the directory couldn't be read.
+    pub error: Option<WalkError>,
+    pub digest: Option<String>,
+    pub size: Option<u64>,
+    /// The target of a symlink and what became of it.
+    pub link: Option<(String, LinkState)>,
+}
+
+impl Node {
+    fn new(entry: &Entry) -> Self {
+        Self {
+            name: entry.name.to_string(),
+            display_name: entry.display_name.to_string(),
+            path: entry.path.to_string(),
+            kind: entry.kind,
+            children: Vec::new(),
+            truncation: entry.is_at_depth_limit.then_some(Truncation::Depth),
+            error: None,
+            digest: entry.digest.map(str::to_string),
+            size: entry.size,
+            link: entry.link.map(|link| (link.target.to_string(), link.state)),
+        }
+    }
+
+    /// A directory, or a symlink to one that was followed, both can list entries.
+    pub fn is_directory(&self) -> bool {
+        self.kind == EntryKind::Directory || matches!(self.link, Some((_, LinkState::Followed)))
+    }
+
+    /// Counts the directories, including this one, and files in the tree.
+    pub fn count(&self) -> (usize, usize) {
+        if !self.is_directory() {
+            return (0, 1);
+        }
+
+        self.children
+            .iter()
+            .map(Node::count)
+            .fold((1, 0), |(dirs, files), (child_dirs, child_files)| {
+                (dirs + child_dirs, files + child_files)
+            })
+    }
+}
+
+/// The whole walk, handed to a `TreeWriter` once it is done.
+pub struct Tree<'a> {
+    /// Every base directory in the order they were walked, usually just one.
+    pub roots: &'a [Node],
+    pub is_total_limit_reached: bool,
+    pub report: Option<&'a Report>,
+}
+
+impl Tree<'_> {
+    /// Counts the directories, not including the base directories like `tree`, and files.
+    pub fn count(&self) -> (usize, usize) {
+        self.roots
+            .iter()
+            .map(Node::count)
+            .fold((0, 0), |(dirs, files), (root_dirs, root_files)| {
+                (dirs + root_dirs - 1, files + root_files)
+            })
+    }
+}
+
+pub type TreeWriter = Box<dyn Fn(&mut dyn Write, &Tree) -> io::Result<()>>;
+
+/// Collects the walk into `Node`s for the formats that need to see a directory as a whole,
+/// then writes it all at once with a `TreeWriter`.
+pub struct TreeOutput {
+    writer: Box<dyn Write>,
+    tree_writer: TreeWriter,
+    // The base directories that were already walked.
+    roots: Vec<Node>,
+    // The base directory followed by the directories being listed, deepest last.
+    stack: Vec<Node>,
+    is_total_limit_reached: bool,
+}
+
+impl TreeOutput {
+    pub fn new(writer: Box<dyn Write>, tree_writer: TreeWriter) -> Self {
+        Self {
+            writer,
+            tree_writer,
+            roots: Vec::new(),
+            stack: Vec::new(),
+            is_total_limit_reached: false,
+        }
+    }
+
+    fn get_current_directory(&mut self) -> &mut Node {
+        self.stack
+            .last_mut()
+            .expect("The base directory should stay on the stack.")
+    }
+}
+
+impl Output for TreeOutput {
+    fn begin(&mut self, root: &Entry) -> io::Result<()> {
+        self.roots.extend(self.stack.pop());
+        self.stack.push(Node::new(root));
+        Ok(())
+    }
+
+    fn entry(&mut self, entry: &Entry) -> io::Result<()> {
+        let node = Node::new(entry);
+        self.get_current_directory().children.push(node);
+        Ok(())
+    }
+
+    fn enter_directory(&mut self) -> io::Result<()> {
+        let directory = self
+            .get_current_directory()
+            .children
+            .pop()
+            .expect("A directory should be entered right after its entry.");
+        self.stack.push(directory);
+        Ok(())
+    }
+
+    fn leave_directory(&mut self) -> io::Result<()> {
+        let directory = self
+            .stack
+            .pop()
+            .expect("Every directory left should have been entered.");
+        self.get_current_directory().children.push(directory);
+        Ok(())
+    }
+
+    fn unreadable(&mut self, error: WalkError, _depth: usize) -> io::Result<()> {
+        self.get_current_directory().error = Some(error);
+        Ok(())
+    }
+
+    fn hidden_entries(&mut self, hidden: &HiddenEntries, _depth: usize) -> io::Result<()> {
+        self.get_current_directory().truncation = Some(Truncation::Hidden(*hidden));
+        Ok(())
+    }
+
+    fn total_limit_reached(&mut self) -> io::Result<()> {
+        self.get_current_directory().truncation = Some(Truncation::TotalLimit);
+        self.is_total_limit_reached = true;
+        Ok(())
+    }
+
+    fn finish(&mut self, report: Option<&Report>) -> io::Result<()> {
+        let root = self
+            .stack
+            .pop()
+            .expect("The base directory should stay on the stack.");
+        self.roots.push(root);
+        let tree = Tree {
+            roots: &self.roots,
+            is_total_limit_reached: self.is_total_limit_reached,
+            report,
+        };
+
+        (self.tree_writer)(&mut self.writer, &tree)?;
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,118 @@
+//! `--output xml` writes the same document as `tree -X`, so tools built around it keep working.
+use super::tree::{Node, Tree};
+use super::EntryKind;
+use std::io::{self, Write};
+
+pub fn write_xml(writer: &mut dyn Write, tree: &Tree) -> io::Result<()> {
+    writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+    writeln!(writer, "<tree>")?;
+    for root in tree.roots {
+        write_node(writer, root, 1)?;
+    }
+
+    let (dirs, files) = tree.count();
+    writeln!(writer, "  <report>")?;
+    writeln!(writer, "    <directories>{dirs}</directories>")?;
+    writeln!(writer, "    <files>{files}</files>")?;
+    if let Some(report) = tree.report {
+        writeln!(writer, "    <displayed>{}</displayed>", report.displayed)?;
+        writeln!(
+            writer,
+            "    <hidden directories=\"{}\" files=\"{}\">{}</hidden>",
+            report.hidden_dirs,
+            report.hidden_files,
+            report.hidden_dirs + report.hidden_files
+        )?;
+        writeln!(
+            writer,
+            "    <seconds>{:.2}</seconds>",
+            report.elapsed_seconds
+        )?;
+        if report.errors.get_count() > 0 {
+            writeln!(writer, "    <errors>{}</errors>", report.errors.get_count())?;
+        }
+    }
+    writeln!(writer, "  </report>")?;
+
+    writeln!(writer, "</tree>")
+}
+
+fn write_node(writer: &mut dyn Write, node: &Node, depth: usize) -> io::Result<()> {
+    let indent = "  ".repeat(depth);
+    let element = match node.kind {
+        EntryKind::Directory => "directory",
+        EntryKind::Symlink => "link",
+        EntryKind::File | EntryKind::Other => "file",
+    };
+
+    // The same attributes as `tree -X -s`, in the same order.
+    let mut attributes = format!("name=\"{}\"", escape_xml(&node.name));
+    if let Some((ref target, _)) = node.link {
+        attributes.push_str(&format!(" target=\"{}\"", escape_xml(target)));
+    }
+    if let Some(size) = node.size {
+        attributes.push_str(&format!(" size=\"{size}\""));
+    }
+
+    // Followed links list their entries the same way as directories.
+    if !node.is_directory() {
+        return writeln!(writer, "{indent}<{element} {attributes}/>");
+    }
+
+    if node.children.is_empty() && node.error.is_none() {
+        return writeln!(writer, "{indent}<{element} {attributes}></{element}>");
+    }
+
+    writeln!(writer, "{indent}<{element} {attributes}>")?;
+    if node.error.is_some() {
+        writeln!(writer, "{indent}  <error>opening dir</error>")?;
+    }
+    for child in &node.children {
+        write_node(writer, child, depth + 1)?;
+    }
+    writeln!(writer, "{indent}</{element}>")
+}
+
+/// Escapes the characters with a special meaning in XML text and attributes.
+///
+/// Control characters other than tab and new lines can't be written in XML 1.0, not even as
+/// references, so they become `?` like in the text tree and can't reach a terminal either.
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c.is_control() => escaped.push('?'),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("main.rs"), "main.rs");
+        assert_eq!(
+            escape_xml("<a & 'b' \"c\">"),
+            "&lt;a &amp; &apos;b&apos; &quot;c&quot;&gt;"
+        );
+    }
+
+    #[test]
+    fn test_escape_xml_control_characters() {
+        assert_eq!(escape_xml("esc\x1b[31mred"), "esc?[31mred");
+        assert_eq!(escape_xml("nul\0del\x7f\u{9b}"), "nul?del??");
+        assert_eq!(escape_xml("tab\tline\n"), "tab\tline\n");
+    }
+}
@@ -0,0 +1,231 @@
+//! Names are written so that they can't mess up the terminal, whatever bytes they hold.
+//!
+//! A name is split into runs of valid UTF-8 and the bytes in between, which only happen on
+//! unix. Every style but `literal` keeps control characters, like the escape sequences of
+//! a terminal or a newline, from reaching it as they are.
+use std::ffi::OsStr;
+
+/// Set with `--quote`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum QuoteStyle {
+    /// Control characters and bytes that aren't UTF-8 become `?`, like `ls -q`.
+    Replace,
+    /// As is, only bytes that aren't UTF-8 are replaced with `�`.
+    Literal,
+    /// Backslash escapes without any quotes, like `ls -b`.
+    Escape,
+    /// Quoted only when needed, so it can be pasted into a shell, like `ls --quoting-style=shell-escape`.
+    Shell,
+    /// Always in double quotes with the escapes of C, like `ls -Q`.
+    C,
+}
+
+impl QuoteStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "replace" => Some(Self::Replace),
+            "literal" => Some(Self::Literal),
+            "escape" => Some(Self::Escape),
+            "shell" => Some(Self::Shell),
+            "c" => Some(Self::C),
+            _ => None,
+        }
+    }
+}
+
+pub fn quote_name(name: &OsStr, style: QuoteStyle) -> String {
+    quote_bytes(name.as_encoded_bytes(), style)
+}
+
+pub fn quote_bytes(bytes: &[u8], style: QuoteStyle) -> String {
+    match style {
+        QuoteStyle::Replace => replace_bytes(bytes),
+        QuoteStyle::Literal => String::from_utf8_lossy(bytes).into_owned(),
+        QuoteStyle::Escape => escape_bytes(bytes, ' '),
+        QuoteStyle::Shell => quote_shell(bytes),
+        QuoteStyle::C => format!("\"{}\"", escape_bytes(bytes, '"')),
+    }
+}
+
+fn replace_bytes(bytes: &[u8]) -> String {
+    let mut replaced = String::with_capacity(bytes.len());
+
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            replaced.push(if c.is_control() { '?' } else { c });
+        }
+        replaced.push_str(&"?".repeat(chunk.invalid().len()));
+    }
+
+    replaced
+}
+
+/// Escapes backslashes, control characters, bytes that aren't UTF-8 and the quote.
+fn escape_bytes(bytes: &[u8], quote: char) -> String {
+    let mut escaped = String::with_capacity(bytes.len());
+
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                c if c == quote => {
+                    escaped.push('\\');
+                    escaped.push(c);
+                }
+                c if c.is_control() => escaped.push_str(&escape_control(c)),
+                c => escaped.push(c),
+            }
+        }
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\{byte:03o}"));
+        }
+    }
+
+    escaped
+}
+
+/// `\n`, `\t` and `\r`, or the bytes of the character in octal, like `\033`.
+fn escape_control(c: char) -> String {
+    match c {
+        '\n' => "\\n".to_string(),
+        '\t' => "\\t".to_string(),
+        '\r' => "\\r".to_string(),
+        c => c
+            .encode_utf8(&mut [0; 4])
+            .bytes()
+            .map(|byte| format!("\\{byte:03o}"))
+            .collect(),
+    }
+}
+
+/// `'sp ace.txt'`, with `$'\n'` for control characters and bytes that aren't UTF-8, which
+/// can't be written inside of single quotes.
+fn quote_shell(bytes: &[u8]) -> String {
+    let is_safe = |c: char| c.is_alphanumeric() || "_-+./,:@%=".contains(c);
+
+    if let Ok(name) = std::str::from_utf8(bytes) {
+        if !name.is_empty() && name.chars().all(is_safe) {
+            return name.to_string();
+        }
+    }
+
+    let mut quoted = String::with_capacity(bytes.len() + 2);
+    let mut is_in_quotes = false;
+
+    let push_unquoted = |quoted: &mut String, text: &str, is_in_quotes: &mut bool| {
+        if *is_in_quotes {
+            quoted.push('\'');
+            *is_in_quotes = false;
+        }
+        quoted.push_str(text);
+    };
+
+    for chunk in bytes.utf8_chunks() {
+        for c in chunk.valid().chars() {
+            if c == '\'' {
+                push_unquoted(&mut quoted, "\\'", &mut is_in_quotes);
+            } else if c.is_control() {
+                let text = format!("$'{}'", escape_control(c));
+                push_unquoted(&mut quoted, &text, &mut is_in_quotes);
+            } else {
+                if !is_in_quotes {
+                    quoted.push('\'');
+                    is_in_quotes = true;
+                }
+                quoted.push(c);
+            }
+        }
+        for byte in chunk.invalid() {
+            let text = format!("$'\\{byte:03o}'");
+            push_unquoted(&mut quoted, &text, &mut is_in_quotes);
+        }
+    }
+
+    if is_in_quotes {
+        quoted.push('\'');
+    }
+
+    if quoted.is_empty() {
+        quoted.push_str("''");
+    }
+
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Not UTF-8: a lone continuation byte, a truncated sequence and a byte that never is.
+    const INVALID: &[u8] = b"a\x80b\xe2\x82c\xff";
+
+    #[test]
+    fn test_quote_plain_names() {
+        for style in [
+            QuoteStyle::Replace,
+            QuoteStyle::Literal,
+            QuoteStyle::Escape,
+            QuoteStyle::Shell,
+        ] {
+            assert_eq!(quote_bytes(b"main.rs", style), "main.rs");
+            assert_eq!(quote_bytes("naïve.txt".as_bytes(), style), "naïve.txt");
+        }
+        assert_eq!(quote_bytes(b"main.rs", QuoteStyle::C), "\"main.rs\"");
+    }
+
+    #[test]
+    fn test_quote_control_characters() {
+        let name = b"bad\x1b[31mname\n";
+        assert_eq!(quote_bytes(name, QuoteStyle::Replace), "bad?[31mname?");
+        assert_eq!(quote_bytes(name, QuoteStyle::Literal), "bad\x1b[31mname\n");
+        assert_eq!(quote_bytes(name, QuoteStyle::Escape), "bad\\033[31mname\\n");
+        assert_eq!(
+            quote_bytes(name, QuoteStyle::Shell),
+            "'bad'$'\\033''[31mname'$'\\n'"
+        );
+        assert_eq!(quote_bytes(name, QuoteStyle::C), "\"bad\\033[31mname\\n\"");
+    }
+
+    #[test]
+    fn test_quote_invalid_bytes() {
+        assert_eq!(quote_bytes(INVALID, QuoteStyle::Replace), "a?b??c?");
+        assert_eq!(quote_bytes(INVALID, QuoteStyle::Literal), "a�b�c�");
+        assert_eq!(
+            quote_bytes(INVALID, QuoteStyle::Escape),
+            "a\\200b\\342\\202c\\377"
+        );
+        assert_eq!(
+            quote_bytes(INVALID, QuoteStyle::Shell),
+            "'a'$'\\200''b'$'\\342'$'\\202''c'$'\\377'"
+        );
+        assert_eq!(
+            quote_bytes(INVALID, QuoteStyle::C),
+            "\"a\\200b\\342\\202c\\377\""
+        );
+    }
+
+    #[test]
+    fn test_quote_special_characters() {
+        assert_eq!(quote_bytes(b"sp ace", QuoteStyle::Escape), "sp\\ ace");
+        assert_eq!(
+            quote_bytes(b"back\\slash", QuoteStyle::Escape),
+            "back\\\\slash"
+        );
+        assert_eq!(quote_bytes(b"sp ace*", QuoteStyle::Shell), "'sp ace*'");
+        assert_eq!(quote_bytes(b"it's", QuoteStyle::Shell), "'it'\\''s'");
+        assert_eq!(quote_bytes(b"", QuoteStyle::Shell), "''");
+        assert_eq!(
+            quote_bytes(b"say \"hi\"", QuoteStyle::C),
+            "\"say \\\"hi\\\"\""
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_quote_name() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let name = OsStr::from_bytes(INVALID);
+        assert_eq!(quote_name(name, QuoteStyle::Replace), "a?b??c?");
+    }
+}
@@ -1,197 +1,606 @@
 // basic idea: depth first search the files in root_dir
+use super::columns::Columns;
 use super::config::Config;
-use super::utils::Limit;
+use super::dir_hash::{DirHashMode, DirHasher};
+use super::filter::EntryFilter;
+use super::hash::{hash_file, HashAlgorithm, HashStats};
+use super::output::{
+    self, Entry, EntryKind, HiddenEntries, HiddenReason, Link, LinkState, Output, Report,
+};
+use super::quote::{quote_name, QuoteStyle};
+use super::size::SizeCalculator;
+use super::sort::{sort_entries, SortKey, SortOptions};
+use super::utils::{get_dir_id, DirId, Limit};
+use super::walk_error::{ErrorTally, WalkError};
+use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::time;
 
 pub struct Renderer {
-    draw_layer_table: Vec<bool>,
+    output: Box<dyn Output>,
     dir_depth_limit: Limit,
     dir_len_limit: Limit,
     total_len_limit: Limit,
+    sort_options: SortOptions,
+    entry_filter: EntryFilter,
+    // Whether a directory has anything left to show after filtering, only used with `--prune`.
+    prune_cache: HashMap<PathBuf, bool>,
+    hash_algorithm: Option<HashAlgorithm>,
+    hash_stats: HashStats,
+    // Hashes the whole tree before anything is shown, with `--dir-hash`.
+    dir_hasher: Option<DirHasher>,
+    // Adds up the size of every directory, with `-s` and `--sort total`.
+    size_calculator: Option<SizeCalculator>,
+    is_size_shown: bool,
+    columns: Columns,
+    is_following_links: bool,
+    quote_style: QuoteStyle,
+    // The directories being listed, deepest last, so `-l` notices links back into one of them.
+    dir_ids: Vec<DirId>,
+    errors: ErrorTally,
+    // Also writes every error to standard error, with `--print-errors`.
+    is_printing_errors: bool,
+    roots_displayed: usize,
+    // The lines shown for the base directories before the current one, when each one has
+    // its own `-T` limit.
+    earlier_roots_lines: usize,
+    dirs_displayed: usize,
+    files_displayed: usize,
+    hidden_dirs: usize,
+    hidden_files: usize,
     start_time: time::Instant,
 }
 
 impl Renderer {
-    fn new(
-        draw_layer_table: Vec<bool>,
-        dir_depth_limit: Limit,
-        dir_len_limit: Limit,
-        total_len_limit: Limit,
-    ) -> Self {
+    fn new(config: &Config, output: Box<dyn Output>, root_path: PathBuf) -> Self {
+        let filter_options = config
+            .get_filter_options()
+            .expect("Config should be in render mode.");
+        let sort_options = config
+            .get_sort_options()
+            .expect("Config should be in render mode.");
+        let size_options = config
+            .get_size_options()
+            .expect("Config should be in render mode.");
+
         Self {
-            draw_layer_table,
-            dir_depth_limit,
-            dir_len_limit,
-            total_len_limit,
+            output,
+            // Create three limits to easily track the bounds of the tree.
+            dir_depth_limit: Limit::new(config.get_max_depth()),
+            dir_len_limit: Limit::new(config.get_dir_len_limit()),
+            total_len_limit: Limit::new(config.get_total_len_limit()),
+            sort_options,
+            size_calculator: new_size_calculator(config, root_path.clone()),
+            entry_filter: EntryFilter::new(filter_options, root_path),
+            prune_cache: HashMap::new(),
+            hash_algorithm: config.get_hash_algorithm(),
+            hash_stats: HashStats::default(),
+            dir_hasher: None,
+            is_size_shown: size_options.is_shown,
+            columns: Columns::new(
+                config
+                    .get_column_options()
+                    .expect("Config should be in render mode."),
+                size_options.is_shown.then_some(size_options.units),
+            ),
+            is_following_links: config.get_is_following_links(),
+            quote_style: config
+                .get_quote_style()
+                .expect("Config should be in render mode."),
+            dir_ids: Vec::new(),
+            errors: ErrorTally::default(),
+            is_printing_errors: config.get_is_printing_errors(),
+            roots_displayed: 0,
+            earlier_roots_lines: 0,
+            dirs_displayed: 0,
+            files_displayed: 0,
+            hidden_dirs: 0,
+            hidden_files: 0,
             start_time: time::Instant::now(),
         }
     }
 
-    pub fn render_directory(config: Config) {
-        let mut path: PathBuf = PathBuf::from(
+    /// Starts over for the next base directory, while the counts of the report carry on.
+    fn set_root(&mut self, config: &Config, root_path: PathBuf) {
+        if let Some(dir_hasher) = self.dir_hasher.take() {
+            self.hash_stats.add(&dir_hasher.get_hash_stats());
+        }
+
+        self.size_calculator = new_size_calculator(config, root_path.clone());
+        self.entry_filter = EntryFilter::new(
             config
-                .get_root_dir()
+                .get_filter_options()
                 .expect("Config should be in render mode."),
+            root_path,
         );
+        self.prune_cache.clear();
 
-        // Create three limits to easily track the bounds of the tree.
-        let dir_depth_limit: Limit = Limit::new(config.get_max_depth());
-        let dir_len_limit: Limit = Limit::new(config.get_dir_len_limit());
-        let total_len_limit: Limit = Limit::new(config.get_total_len_limit());
-
-        // Remembers the past to determine if we should draw:
-        // │   ├── file_name
-        // or
-        //     ├── file_name
-        let draw_layer_table = vec![true; dir_depth_limit.get_limit().unwrap()];
-
-        let mut renderer = Renderer::new(
-            draw_layer_table,
-            dir_depth_limit,
-            dir_len_limit,
-            total_len_limit,
-        );
+        if config.get_is_total_limit_per_root() {
+            self.earlier_roots_lines += self.total_len_limit.get_count();
+            self.total_len_limit = Limit::new(config.get_total_len_limit());
+        }
+    }
 
-        // Print the root of the tree.
-        println!(
-            "{}",
-            path.to_str().expect("Config should be in render mode.")
+    /// Shows every base directory as its own tree, one after the other.
+    ///
+    /// Gives back the errors the walk ran into, they are already marked in the trees.
+    pub fn render_directory(config: Config, writer: Box<dyn Write>) -> io::Result<ErrorTally> {
+        let root_dirs = config.get_root_dirs();
+
+        let output = output::new_output(
+            config
+                .get_output_options()
+                .expect("Config should be in render mode."),
+            writer,
         );
 
-        let rendered_full_dir = renderer.scan_directory(&mut path);
+        let base_dir = config.get_base_dir();
+        let mut renderer = Renderer::new(&config, output, base_dir.join(&root_dirs[0]));
+
+        for (index, root_dir) in root_dirs.iter().enumerate() {
+            let path = base_dir.join(root_dir);
+            if index > 0 {
+                renderer.set_root(&config, path.clone());
+            }
 
-        if !rendered_full_dir {
-            renderer.render_limit_reached();
+            // A shared `-T` limit leaves out the base directories after it was reached.
+            if !renderer.render_root(&config, root_dir, path)?
+                && !config.get_is_total_limit_per_root()
+            {
+                break;
+            }
         }
 
-        if config.get_is_verbose() {
-            println!("\n{}", renderer.get_statistics_message());
+        let report = renderer.get_report();
+        renderer
+            .output
+            .finish(config.get_is_verbose().then_some(&report))?;
+
+        Ok(renderer.errors)
+    }
+
+    /// Returns `false` once the `-T` limit is reached, like `scan_directory`.
+    ///
+    /// The base directory is shown as root_dir, the way it was given, even when path also
+    /// holds the directory it is relative to.
+    fn render_root(
+        &mut self,
+        config: &Config,
+        root_dir: &str,
+        mut path: PathBuf,
+    ) -> io::Result<bool> {
+        self.roots_displayed += 1;
+
+        let mut root_digest = None;
+        if config
+            .get_dir_hash_options()
+            .is_some_and(|options| options.mode == DirHashMode::Tree)
+        {
+            let mut dir_hasher = DirHasher::new(config, path.clone());
+            root_digest = Some(dir_hasher.hash_tree(&path)?);
+            self.dir_hasher = Some(dir_hasher);
         }
+
+        // Asking for the base directory first adds up the whole tree, see `SizeCalculator`.
+        let root_size = self.get_size(&path);
+        let root_columns = self.format_columns(&path, root_size);
+
+        // Print the root of the tree.
+        self.output.begin(&Entry {
+            name: root_dir,
+            display_name: &quote_name(root_dir.as_ref(), self.quote_style),
+            path: ".",
+            kind: EntryKind::Directory,
+            depth: 0,
+            is_last_in_dir: true,
+            is_at_depth_limit: false,
+            link: None,
+            digest: root_digest.as_deref(),
+            size: root_size,
+            columns: &root_columns,
+        })?;
+
+        self.scan_directory(&mut path)
     }
 
+    /// Returns `false` once the `-T` limit is reached, the walk stops there.
     #[allow(clippy::needless_borrows_for_generic_args)]
-    fn scan_directory(&mut self, path: &mut PathBuf) -> bool {
+    fn scan_directory(&mut self, path: &mut PathBuf) -> io::Result<bool> {
         // Get a list of files and sub directoris at the directory at path.
-        let entries = match fs::read_dir(&path) {
-            Ok(read_dir) => read_dir
-                .collect::<Result<Vec<_>, std::io::Error>>()
-                .unwrap(),
-            Err(_) => {
-                let r = self.render_line("[[RESTRICTED]]", true, false);
-                return r;
+        let read_dir = match fs::read_dir(&path) {
+            Ok(read_dir) => read_dir,
+            Err(error) => {
+                let error = self.record_error(path, &error);
+                if !self.count_line()? {
+                    return Ok(false);
+                }
+                self.output
+                    .unreadable(error, self.dir_depth_limit.get_count())?;
+                return Ok(true);
             }
         };
+        let entries = self.read_entries(path, read_dir);
+
+        let dir_id = self.is_following_links.then(|| get_dir_id(path)).flatten();
+        let is_dir_tracked = dir_id.is_some();
+        self.dir_ids.extend(dir_id);
+
+        self.entry_filter.enter_directory(path);
+        let rendered_full_dir = self.render_entries(path, entries);
+        self.entry_filter.leave_directory();
+
+        if is_dir_tracked {
+            self.dir_ids.pop();
+        }
+
+        rendered_full_dir
+    }
+
+    /// Filters, sorts and draws the entries of the directory at path, recursing into sub directories.
+    fn render_entries(
+        &mut self,
+        path: &mut PathBuf,
+        entries: Vec<fs::DirEntry>,
+    ) -> io::Result<bool> {
+        let mut entries = self.filter_entries(path, entries);
+
+        let size_calculator = &mut self.size_calculator;
+        sort_entries(&mut entries, &self.sort_options, &mut |entry| {
+            size_calculator.as_mut().map_or(0, |size_calculator| {
+                size_calculator.get_total_size(&entry.path())
+            })
+        });
 
         let entries_len = entries.len();
 
         if entries_len == 0 {
-            return true;
+            return Ok(true);
+        }
+
+        let depth = self.dir_depth_limit.get_count();
+
+        // Only happens for the root with `-D 0`, deeper directories are never scanned past the limit.
+        if !self.dir_depth_limit.is_under_limit() {
+            if !self.count_line()? {
+                return Ok(false);
+            }
+            let hidden = count_entries(&entries, HiddenReason::Depth);
+            self.output.hidden_entries(&hidden, depth)?;
+            return Ok(true);
         }
 
         let last_entry_index = entries_len - 1;
 
+        // Each directory gets its own count, so recursing doesn't eat into the parent's limit.
+        let mut dir_len_limit = Limit::new(self.dir_len_limit.get_limit());
+
         for (index, entry) in entries.iter().enumerate() {
-            let file_type = entry.file_type().unwrap();
-            let file_name = &entry.file_name().into_string().unwrap()[..];
+            if !dir_len_limit.is_under_limit() {
+                // The summary takes up a line like any entry.
+                if !self.count_line()? {
+                    return Ok(false);
+                }
+                let hidden = count_entries(&entries[index..], HiddenReason::DirLimit);
+                self.hidden_dirs += hidden.dirs;
+                self.hidden_files += hidden.files;
+                self.output.hidden_entries(&hidden, depth)?;
+                break;
+            }
+            dir_len_limit.increment();
+
+            // Already read by `read_entries`, it is only missing if the entry changed since.
+            let file_type = entry.file_type().ok();
+            let file_name = entry.file_name();
+            let name = file_name.to_string_lossy();
+            let kind = EntryKind::from_file_type(file_type);
             let is_last_in_dir = last_entry_index == index;
 
-            self.draw_layer_table[self.dir_depth_limit.get_count()] = !is_last_in_dir;
-            // if is_last_in_dir {
-            // self.draw_layer_table[self.dir_depth_limit.get_count()] = false;
-            // Logically this shouldn't work, but it seems to work perfectly!
-            // }
+            if !self.count_line()? {
+                return Ok(false);
+            }
+
+            path.push(&file_name);
 
-            let is_dir = file_type.is_dir();
+            let link = (kind == EntryKind::Symlink).then(|| self.get_link(path));
+            // Links followed with `-l` are listed the same way as directories.
+            let is_dir = file_type.is_some_and(|file_type| file_type.is_dir())
+                || link
+                    .as_ref()
+                    .is_some_and(|link| link.state == LinkState::Followed);
 
-            let continue_render = self.render_line(file_name, is_last_in_dir, is_dir);
-            if !continue_render {
-                return false;
+            if is_dir {
+                self.dirs_displayed += 1;
+            } else {
+                self.files_displayed += 1;
             }
 
-            if !is_dir {
-                continue;
+            let relative_path = self.entry_filter.get_relative_path(path);
+            let digest = self.get_digest(path, kind);
+            let size = self.get_size(path);
+            self.output.entry(&Entry {
+                name: &name,
+                display_name: &quote_name(&file_name, self.quote_style),
+                path: &relative_path,
+                kind,
+                depth,
+                is_last_in_dir,
+                is_at_depth_limit: is_dir && self.dir_depth_limit.is_at_limit(),
+                link: link.as_ref().map(|link| Link {
+                    target: &link.target,
+                    display_target: &link.display_target,
+                    state: link.state,
+                }),
+                digest: digest.as_deref(),
+                size,
+                columns: &self.format_columns(path, size),
+            })?;
+
+            let mut rendered_full_dir = true;
+
+            if is_dir {
+                self.dir_depth_limit.increment();
+                if self.dir_depth_limit.is_under_limit() {
+                    self.output.enter_directory()?;
+                    rendered_full_dir = self.scan_directory(path)?;
+                    self.output.leave_directory()?;
+                }
+                self.dir_depth_limit.decrement();
+            }
+
+            path.pop();
+
+            if !rendered_full_dir {
+                return Ok(false);
             }
+        }
+
+        Ok(true)
+    }
 
-            self.dir_depth_limit.increment();
-            if self.dir_depth_limit.is_under_limit() {
-                path.push(file_name);
-                self.scan_directory(path);
-                path.pop();
+    /// The entries of a directory that could be read, the others are left out and recorded.
+    fn read_entries(&mut self, path: &Path, read_dir: fs::ReadDir) -> Vec<fs::DirEntry> {
+        let mut entries = Vec::new();
+
+        for entry in read_dir {
+            match entry {
+                Ok(entry) => match entry.file_type() {
+                    Ok(_) => entries.push(entry),
+                    Err(error) => {
+                        self.record_error(&entry.path(), &error);
+                    }
+                },
+                // Nothing is known about the entry, not even its name.
+                Err(error) => {
+                    self.record_error(path, &error);
+                }
             }
-            self.dir_depth_limit.decrement();
         }
-        self.dir_len_limit.reset_count();
 
-        true
+        entries
+    }
+
+    /// Counts the error for the `-v` report and the exit code, and writes it to standard
+    /// error with `--print-errors`.
+    fn record_error(&mut self, path: &Path, error: &io::Error) -> WalkError {
+        let error = WalkError::from_io_error(error);
+        self.errors.add(error);
+
+        if self.is_printing_errors {
+            eprintln!(
+                "mtree: {}: {}",
+                quote_name(path.as_os_str(), self.quote_style),
+                error.get_message()
+            );
+        }
+
+        error
     }
 
-    fn render_line(&mut self, file_name: &str, is_last_in_dir: bool, is_dir: bool) -> bool {
+    /// Counts a line towards the `-T` limit, telling the output when there is no room left.
+    fn count_line(&mut self) -> io::Result<bool> {
         if !self.total_len_limit.is_under_limit() {
-            return false;
+            self.output.total_limit_reached()?;
+            return Ok(false);
         }
 
         self.total_len_limit.increment();
 
-        assert!(
-            self.dir_depth_limit
-                .get_limit()
-                .expect("Tree should have a depth limit.")
-                <= self.draw_layer_table.len()
-        );
-
-        let mut print_buffer: String = String::new();
+        Ok(true)
+    }
 
-        for &render_layer in self
-            .draw_layer_table
-            .iter()
-            .take(self.dir_depth_limit.get_count())
-        {
-            if render_layer {
-                print_buffer.push_str("│   ");
-            } else {
-                print_buffer.push_str("    ");
+    /// Hashes regular files with `--hash`, anything else would block or not be a file at all.
+    /// Directories, and files that were already hashed, come from the `--dir-hash` hashes.
+    fn get_digest(&mut self, path: &Path, kind: EntryKind) -> Option<String> {
+        if let Some(ref dir_hasher) = self.dir_hasher {
+            match kind {
+                EntryKind::Directory => return dir_hasher.get_digest(path),
+                EntryKind::File if self.hash_algorithm.is_some() => {
+                    if let Some(digest) = dir_hasher.get_digest(path) {
+                        return Some(digest);
+                    }
+                }
+                _ => (),
             }
         }
 
-        if is_last_in_dir {
-            print_buffer.push_str("└── ");
-        } else {
-            print_buffer.push_str("├── ");
+        let hash_algorithm = self.hash_algorithm.filter(|_| kind == EntryKind::File)?;
+        let hex = hash_file(path, hash_algorithm, &mut self.hash_stats).ok()?;
+        Some(format!("{}:{hex}", hash_algorithm.get_name()))
+    }
+
+    /// Where the symlink at path points, and whether `-l` lists what is there.
+    fn get_link(&self, path: &Path) -> LinkTarget {
+        let target = fs::read_link(path).unwrap_or_default();
+
+        let state = match fs::metadata(path) {
+            Err(_) => LinkState::Broken,
+            Ok(metadata) if self.is_following_links && metadata.is_dir() => {
+                if get_dir_id(path).is_some_and(|dir_id| self.dir_ids.contains(&dir_id)) {
+                    LinkState::Recursive
+                } else {
+                    LinkState::Followed
+                }
+            }
+            Ok(_) => LinkState::Valid,
+        };
+
+        LinkTarget {
+            target: target.to_string_lossy().into_owned(),
+            display_target: quote_name(target.as_os_str(), self.quote_style),
+            state,
         }
+    }
 
-        print_buffer.push_str(file_name);
+    /// Only reads the metadata again when a column needs it.
+    fn format_columns(&self, path: &Path, size: Option<u64>) -> String {
+        let metadata = self
+            .columns
+            .needs_metadata()
+            .then(|| fs::symlink_metadata(path).ok())
+            .flatten();
 
-        if is_dir && self.dir_depth_limit.is_at_limit() {
-            print_buffer.push_str(" ...");
+        self.columns.format(metadata.as_ref(), size)
+    }
+
+    /// The size of a file or the total of a directory, only with `-s`.
+    fn get_size(&mut self, path: &Path) -> Option<u64> {
+        if !self.is_size_shown {
+            return None;
         }
 
-        println!("{print_buffer}");
+        self.size_calculator
+            .as_mut()
+            .map(|size_calculator| size_calculator.get_total_size(path))
+    }
 
-        true
+    /// Drops the entries hidden by `-I`, `-P`, ignore files and `--prune`.
+    fn filter_entries(&mut self, path: &Path, entries: Vec<fs::DirEntry>) -> Vec<fs::DirEntry> {
+        let mut visible_entries = Vec::with_capacity(entries.len());
+
+        for entry in entries {
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+            let entry_path = path.join(entry.file_name());
+
+            if !self.entry_filter.is_visible(&entry_path, is_dir) {
+                continue;
+            }
+
+            if is_dir
+                && self.entry_filter.get_options().prune
+                && !self.has_visible_descendants(&entry_path)
+            {
+                continue;
+            }
+
+            visible_entries.push(entry);
+        }
+
+        visible_entries
     }
 
-    pub fn render_limit_reached(&mut self) {
-        for &render_layer in self
-            .draw_layer_table
-            .iter()
-            .take(self.total_len_limit.get_count())
-        {
-            if render_layer {
-                print!("... ");
-            } else {
-                print!("    ");
+    /// Looks through the whole directory, ignoring the depth limit, for a file that passes the filters.
+    fn has_visible_descendants(&mut self, path: &Path) -> bool {
+        if let Some(&has_visible_descendants) = self.prune_cache.get(path) {
+            return has_visible_descendants;
+        }
+
+        let mut has_visible_descendants = false;
+
+        if let Ok(read_dir) = fs::read_dir(path) {
+            self.entry_filter.enter_directory(path);
+
+            for entry in read_dir.flatten() {
+                let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+                let entry_path = entry.path();
+
+                if !self.entry_filter.is_visible(&entry_path, is_dir) {
+                    continue;
+                }
+
+                if !is_dir || self.has_visible_descendants(&entry_path) {
+                    has_visible_descendants = true;
+                    break;
+                }
             }
+
+            self.entry_filter.leave_directory();
         }
+
+        self.prune_cache
+            .insert(path.to_path_buf(), has_visible_descendants);
+
+        has_visible_descendants
     }
 
-    pub fn get_statistics_message(&self) -> String {
-        let elapsed_time = self.start_time.elapsed().as_secs_f32();
-        let amount_of_files_rendered = self.total_len_limit.get_count();
-        format!(
-            "{} files and directories displayed in {:.2} seconds",
-            amount_of_files_rendered, elapsed_time
+    /// Everything hashed for `--hash` and `--dir-hash`, `None` when neither was used.
+    fn get_hash_stats(&self) -> Option<HashStats> {
+        if self.hash_algorithm.is_none() && self.dir_hasher.is_none() {
+            return None;
+        }
+
+        let mut hash_stats = self.hash_stats;
+        if let Some(ref dir_hasher) = self.dir_hasher {
+            hash_stats.add(&dir_hasher.get_hash_stats());
+        }
+
+        Some(hash_stats)
+    }
+
+    pub fn get_report(&self) -> Report {
+        Report {
+            roots: self.roots_displayed,
+            displayed: self.earlier_roots_lines + self.total_len_limit.get_count(),
+            dirs: self.dirs_displayed,
+            files: self.files_displayed,
+            hidden_dirs: self.hidden_dirs,
+            hidden_files: self.hidden_files,
+            elapsed_seconds: self.start_time.elapsed().as_secs_f32(),
+            hash_stats: self.get_hash_stats(),
+            errors: self.errors,
+        }
+    }
+}
+
+/// Adds up the size of every directory, only with `-s` and `--sort total`.
+fn new_size_calculator(config: &Config, root_path: PathBuf) -> Option<SizeCalculator> {
+    let size_options = config
+        .get_size_options()
+        .expect("Config should be in render mode.");
+    let sort_options = config
+        .get_sort_options()
+        .expect("Config should be in render mode.");
+
+    (size_options.is_shown || sort_options.key == SortKey::Total).then(|| {
+        SizeCalculator::new(
+            size_options,
+            config
+                .get_filter_options()
+                .expect("Config should be in render mode."),
+            root_path,
+            config.get_is_following_links(),
         )
+    })
+}
+
+/// A symlink read by `get_link`, owning what an `output::Link` borrows.
+struct LinkTarget {
+    target: String,
+    display_target: String,
+    state: LinkState,
+}
+
+/// Splits entries that won't be shown into directories and files.
+fn count_entries(entries: &[fs::DirEntry], reason: HiddenReason) -> HiddenEntries {
+    let dirs = entries
+        .iter()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .count();
+
+    HiddenEntries {
+        dirs,
+        files: entries.len() - dirs,
+        reason,
     }
 }
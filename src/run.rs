@@ -1,13 +1,158 @@
 use super::config::Config;
+use super::dir_hash::{DirHashMode, DirHasher};
+use super::du::write_disk_usage;
 use super::render::Renderer;
+use super::spec::write_spec;
+use super::update_file::{update_file, UpdateFileError};
+use super::verify::verify_directory;
+use super::walk_error::{ErrorTally, WalkError};
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+
+/// The file given to `--update-file --check` needs to be updated.
+const EXIT_OUT_OF_DATE: u8 = 1;
+
+/// The directory given to `mtree verify` doesn't match the spec, the same code as BSD mtree.
+const EXIT_DRIFT: u8 = 2;
+
+/// Parts of the tree couldn't be read, the worst kind of error decides the code.
+const EXIT_PERMISSION_DENIED: u8 = 3;
+const EXIT_NOT_FOUND: u8 = 4;
+const EXIT_NOT_A_DIRECTORY: u8 = 5;
+const EXIT_IO_ERROR: u8 = 6;
+
+/// The command line couldn't be read, `EX_USAGE` from sysexits(3).
+const EXIT_USAGE: u8 = 64;
 
 #[allow(unreachable_code)]
-pub fn run(config: Config) {
+pub fn run(config: Config) -> ExitCode {
     if let Some(message) = config.get_message() {
         println!("{message}");
     } else if let Some(error) = config.get_error() {
-        println!("Error: {error}");
+        eprintln!("Error: {error}");
+        eprintln!("Run `mtree --help` to see every option.");
+        return ExitCode::from(EXIT_USAGE);
+    } else if let Some(file) = config.get_update_file() {
+        let (is_up_to_date, errors) = match update_file(&file, config.get_is_check()) {
+            Ok(result) => result,
+            Err(error) => {
+                eprintln!("Error: {error}");
+                return ExitCode::from(match error {
+                    UpdateFileError::Invalid(_) => EXIT_USAGE,
+                    UpdateFileError::Io { error, .. } => get_walk_exit_code(error),
+                });
+            }
+        };
+
+        if !is_up_to_date && config.get_is_check() {
+            eprintln!("`{file}` is out of date, run `mtree --update-file {file}` to update it.");
+        } else if !is_up_to_date {
+            println!("Updated `{file}`.");
+        }
+
+        // Directories the regions couldn't fully read matter more than being out of date.
+        if let Some(error) = errors.get_worst() {
+            return ExitCode::from(get_walk_exit_code(error));
+        }
+        if config.get_is_check() && !is_up_to_date {
+            return ExitCode::from(EXIT_OUT_OF_DATE);
+        }
+    } else if config
+        .get_dir_hash_options()
+        .is_some_and(|options| options.mode == DirHashMode::RootOnly)
+    {
+        let root_dirs = config.get_root_dirs();
+        let mut errors = ErrorTally::default();
+
+        for root_dir in &root_dirs {
+            match DirHasher::get_root_hash(&config, root_dir) {
+                // Several hashes are named like the lines of `sha256sum`.
+                Ok((hex, hash_stats, root_errors)) => {
+                    if root_dirs.len() > 1 {
+                        println!("{hex}  {root_dir}");
+                    } else {
+                        println!("{hex}");
+                    }
+                    if config.get_is_verbose() {
+                        eprintln!("{}", hash_stats.get_message());
+                        if root_errors.get_count() > 0 {
+                            eprintln!("{}", root_errors.get_message());
+                        }
+                    }
+                    errors.add_tally(&root_errors);
+                }
+                Err(error) => {
+                    eprintln!("Error: {error}");
+                    return ExitCode::from(get_walk_exit_code(WalkError::from_io_error(&error)));
+                }
+            }
+        }
+
+        if let Some(error) = errors.get_worst() {
+            return ExitCode::from(get_walk_exit_code(error));
+        }
+    } else if config.get_is_verify() {
+        let mut writer = BufWriter::new(io::stdout());
+        let drift = verify_directory(&config, &mut writer);
+        let _ = writer.flush();
+
+        match drift {
+            Ok(drift) => {
+                if config.get_is_verbose() {
+                    println!("{}", drift.get_message());
+                }
+                // Not being able to look for extra entries is worse than finding some.
+                if let Some(error) = drift.errors.get_worst() {
+                    return ExitCode::from(get_walk_exit_code(error));
+                }
+                if drift.has_drift() {
+                    return ExitCode::from(EXIT_DRIFT);
+                }
+            }
+            Err(error) => {
+                eprintln!("Error: {error}");
+                return ExitCode::FAILURE;
+            }
+        }
     } else {
-        Renderer::render_directory(config);
+        let result = if config.get_spec_keywords().is_some() {
+            let mut writer = BufWriter::new(io::stdout());
+            write_spec(&config, &mut writer).and_then(|(hash_stats, errors)| {
+                // The spec itself goes to standard output, so the report can't.
+                if config.get_is_verbose() && hash_stats.files > 0 {
+                    eprintln!("{}", hash_stats.get_message());
+                }
+                writer.flush().map(|_| errors)
+            })
+        } else if config.get_du_threshold().is_some() {
+            write_disk_usage(&config, &mut BufWriter::new(io::stdout()))
+        } else {
+            Renderer::render_directory(config, Box::new(BufWriter::new(io::stdout())))
+        };
+
+        let errors = match result {
+            Ok(errors) => errors,
+            // Stopping early because the reader went away, like `mtree | head`, isn't an error.
+            Err(error) if error.kind() == io::ErrorKind::BrokenPipe => ErrorTally::default(),
+            Err(error) => {
+                eprintln!("Error: {error}");
+                return ExitCode::from(EXIT_IO_ERROR);
+            }
+        };
+
+        if let Some(error) = errors.get_worst() {
+            return ExitCode::from(get_walk_exit_code(error));
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+fn get_walk_exit_code(error: WalkError) -> u8 {
+    match error {
+        WalkError::PermissionDenied => EXIT_PERMISSION_DENIED,
+        WalkError::NotFound => EXIT_NOT_FOUND,
+        WalkError::NotADirectory => EXIT_NOT_A_DIRECTORY,
+        WalkError::Io(_) => EXIT_IO_ERROR,
     }
 }
@@ -0,0 +1,378 @@
+use super::filter::{EntryFilter, FilterOptions};
+use super::utils::{get_dir_id, DirId};
+use super::walk_error::{ErrorTally, WalkError};
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+
+/// How sizes are written.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SizeUnits {
+    /// The exact number of bytes.
+    Bytes,
+    /// Powers of 1024, `-h`.
+    Iec,
+    /// Powers of 1000, `--si`.
+    Si,
+}
+
+/// What the size of an entry means.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SizeUsage {
+    /// The length of the contents, like `ls -l`.
+    Apparent,
+    /// The blocks taken on disk, like `du`.
+    Disk,
+}
+
+/// Set with `-s`, `-h`, `--si` and `--disk-usage`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SizeOptions {
+    pub is_shown: bool,
+    pub units: SizeUnits,
+    pub usage: SizeUsage,
+}
+
+impl SizeOptions {
+    pub fn new() -> Self {
+        Self {
+            is_shown: false,
+            units: SizeUnits::Bytes,
+            usage: SizeUsage::Apparent,
+        }
+    }
+
+    /// The size of a single entry, without anything inside of it.
+    pub fn get_size(&self, metadata: &Metadata) -> u64 {
+        match self.usage {
+            SizeUsage::Apparent => metadata.len(),
+            SizeUsage::Disk => get_disk_usage(metadata),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn get_disk_usage(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    // `st_blocks` is always in units of 512 bytes, whatever the block size of the file system.
+    metadata.blocks() * 512
+}
+
+#[cfg(not(unix))]
+fn get_disk_usage(metadata: &Metadata) -> u64 {
+    metadata.len()
+}
+
+/// `4.0K`, `12M`, or the exact number of bytes, the same way as `du -h` and `du --si`.
+pub fn format_size(bytes: u64, units: SizeUnits) -> String {
+    let prefixes = match units {
+        SizeUnits::Bytes => return bytes.to_string(),
+        SizeUnits::Iec => ["K", "M", "G", "T", "P", "E"],
+        SizeUnits::Si => ["k", "M", "G", "T", "P", "E"],
+    };
+
+    match scale_size(bytes, units) {
+        Some((value, index)) if value < 10.0 => format!("{value:.1}{}", prefixes[index]),
+        Some((value, index)) => format!("{value:.0}{}", prefixes[index]),
+        None => bytes.to_string(),
+    }
+}
+
+/// `1.3 GiB`, `4.1 kB` or `512 B`, with the unit spelled out.
+pub fn format_size_with_unit(bytes: u64, units: SizeUnits) -> String {
+    let unit_names = match units {
+        SizeUnits::Bytes => return format!("{bytes} B"),
+        SizeUnits::Iec => ["KiB", "MiB", "GiB", "TiB", "PiB", "EiB"],
+        SizeUnits::Si => ["kB", "MB", "GB", "TB", "PB", "EB"],
+    };
+
+    match scale_size(bytes, units) {
+        Some((value, index)) => format!("{value:.1} {}", unit_names[index]),
+        None => format!("{bytes} B"),
+    }
+}
+
+/// Divides the bytes until they are under the base of the units, giving the value and
+/// which prefix it needs, or nothing when they already were.
+fn scale_size(bytes: u64, units: SizeUnits) -> Option<(f64, usize)> {
+    let base = match units {
+        SizeUnits::Bytes => return None,
+        SizeUnits::Iec => 1024.0,
+        SizeUnits::Si => 1000.0,
+    };
+
+    let mut value = bytes as f64;
+    if value < base {
+        return None;
+    }
+
+    let mut index = 0;
+    value /= base;
+    // There are 6 prefixes, enough for any u64.
+    while value >= base && index < 5 {
+        value /= base;
+        index += 1;
+    }
+
+    Some((value, index))
+}
+
+/// Adds up everything inside of directories, remembering the total of every directory it
+/// walks. The first size asked for walks the whole tree from the base directory once, so
+/// the ignore files of every directory on the way are read, after that the totals are
+/// looked up.
+///
+/// Only what the filters and ignore files leave in is counted, the `-D`, `-L` and `-T`
+/// limits don't matter. What can't be read counts as nothing, and is kept in the errors.
+///
+/// Following links counts what links to directories point to, the way `-l` lists them,
+/// except for links back into a directory being added up. A file with several hard links
+/// only counts under the first of its paths walked, like `du`.
+pub struct SizeCalculator {
+    options: SizeOptions,
+    entry_filter: EntryFilter,
+    totals: HashMap<PathBuf, u64>,
+    errors: ErrorTally,
+    root_path: PathBuf,
+    is_root_walked: bool,
+    is_following_links: bool,
+    // The directories being added up, deepest last, only when following links.
+    dir_ids: Vec<DirId>,
+    // The path each file with several hard links was counted under, by device and inode.
+    #[cfg(unix)]
+    linked_files: HashMap<(u64, u64), PathBuf>,
+}
+
+impl SizeCalculator {
+    pub fn new(
+        options: SizeOptions,
+        filter_options: FilterOptions,
+        root_path: PathBuf,
+        is_following_links: bool,
+    ) -> Self {
+        Self {
+            options,
+            entry_filter: EntryFilter::new(filter_options, root_path.clone()),
+            totals: HashMap::new(),
+            errors: ErrorTally::default(),
+            root_path,
+            is_root_walked: false,
+            is_following_links,
+            dir_ids: Vec::new(),
+            #[cfg(unix)]
+            linked_files: HashMap::new(),
+        }
+    }
+
+    /// Every error of the walks so far, each directory is only walked once.
+    pub fn get_errors(&self) -> ErrorTally {
+        self.errors
+    }
+
+    /// The size of a file, or the size of a directory and everything inside of it.
+    pub fn get_total_size(&mut self, path: &Path) -> u64 {
+        // Starting anywhere else would leave out the ignore files of the directories above path.
+        if !self.is_root_walked {
+            self.is_root_walked = true;
+            let root_path = self.root_path.clone();
+            self.add_up(&root_path);
+        }
+
+        self.add_up(path)
+    }
+
+    fn add_up(&mut self, path: &Path) -> u64 {
+        let mut metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(error) => {
+                self.errors.add(WalkError::from_io_error(&error));
+                return 0;
+            }
+        };
+
+        let mut dir_id = None;
+        if self.is_following_links {
+            if let Some(target_metadata) = fs::metadata(path)
+                .ok()
+                .filter(|target_metadata| metadata.is_symlink() && target_metadata.is_dir())
+            {
+                metadata = target_metadata;
+            }
+            dir_id = metadata.is_dir().then(|| get_dir_id(path)).flatten();
+        }
+
+        if !metadata.is_dir() {
+            if self.is_counted_elsewhere(path, &metadata) {
+                return 0;
+            }
+            return self.options.get_size(&metadata);
+        }
+
+        if let Some(&total) = self.totals.get(path) {
+            return total;
+        }
+
+        // A link back into a directory being added up only counts as the link itself.
+        if dir_id
+            .as_ref()
+            .is_some_and(|dir_id| self.dir_ids.contains(dir_id))
+        {
+            let total = fs::symlink_metadata(path)
+                .map_or(0, |link_metadata| self.options.get_size(&link_metadata));
+            self.totals.insert(path.to_path_buf(), total);
+            return total;
+        }
+
+        let is_dir_tracked = dir_id.is_some();
+        self.dir_ids.extend(dir_id);
+
+        let mut total = self.options.get_size(&metadata);
+
+        match fs::read_dir(path) {
+            Ok(read_dir) => {
+                self.entry_filter.enter_directory(path);
+
+                for entry in read_dir {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(error) => {
+                            self.errors.add(WalkError::from_io_error(&error));
+                            continue;
+                        }
+                    };
+                    let entry_path = entry.path();
+                    let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+
+                    if self.entry_filter.is_visible(&entry_path, is_dir) {
+                        total += self.add_up(&entry_path);
+                    }
+                }
+
+                self.entry_filter.leave_directory();
+            }
+            Err(error) => self.errors.add(WalkError::from_io_error(&error)),
+        }
+
+        if is_dir_tracked {
+            self.dir_ids.pop();
+        }
+
+        self.totals.insert(path.to_path_buf(), total);
+        total
+    }
+
+    /// Whether the file is a hard link to one already counted under another path.
+    #[cfg(unix)]
+    fn is_counted_elsewhere(&mut self, path: &Path, metadata: &Metadata) -> bool {
+        use std::os::unix::fs::MetadataExt;
+
+        if metadata.nlink() < 2 {
+            return false;
+        }
+
+        let counted_path = self
+            .linked_files
+            .entry((metadata.dev(), metadata.ino()))
+            .or_insert_with(|| path.to_path_buf());
+        counted_path != path
+    }
+
+    #[cfg(not(unix))]
+    fn is_counted_elsewhere(&mut self, _path: &Path, _metadata: &Metadata) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    #[test]
+    fn test_get_total_size_with_root_ignore_file() {
+        let root_path = env::temp_dir().join(format!("mtree-size-test-{}", process::id()));
+        fs::create_dir_all(root_path.join("a")).unwrap();
+        fs::create_dir_all(root_path.join("b")).unwrap();
+        fs::write(root_path.join(".mtreeignore"), "a/big.bin\n").unwrap();
+        fs::write(root_path.join("a").join("big.bin"), [0; 1000]).unwrap();
+        fs::write(root_path.join("b").join("small.bin"), [0; 10]).unwrap();
+
+        let options = SizeOptions::new();
+        let get_directory_size =
+            |name| options.get_size(&fs::metadata(root_path.join(name)).unwrap());
+        let a_directory_size = get_directory_size("a");
+        let b_directory_size = get_directory_size("b");
+        let filter_options = FilterOptions {
+            use_gitignore: true,
+            ..FilterOptions::new()
+        };
+        let mut size_calculator =
+            SizeCalculator::new(options, filter_options, root_path.clone(), false);
+
+        // Asked before the base directory, `a` still leaves out what its ignore file hides.
+        let a_size = size_calculator.get_total_size(&root_path.join("a"));
+        let b_size = size_calculator.get_total_size(&root_path.join("b"));
+        fs::remove_dir_all(&root_path).unwrap();
+
+        assert_eq!(a_size, a_directory_size);
+        assert_eq!(b_size, b_directory_size + 10);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_get_total_size_with_hard_links() {
+        let root_path = env::temp_dir().join(format!("mtree-size-link-test-{}", process::id()));
+        fs::create_dir_all(root_path.join("a")).unwrap();
+        fs::create_dir_all(root_path.join("b")).unwrap();
+        fs::write(root_path.join("a").join("big.bin"), [0; 1000]).unwrap();
+        fs::hard_link(
+            root_path.join("a").join("big.bin"),
+            root_path.join("b").join("link.bin"),
+        )
+        .unwrap();
+
+        let options = SizeOptions::new();
+        let get_directory_size =
+            |name: &str| options.get_size(&fs::metadata(root_path.join(name)).unwrap());
+        let root_directory_size = get_directory_size("");
+        let a_directory_size = get_directory_size("a");
+        let b_directory_size = get_directory_size("b");
+        let mut size_calculator =
+            SizeCalculator::new(options, FilterOptions::new(), root_path.clone(), false);
+
+        let root_size = size_calculator.get_total_size(&root_path);
+        let a_size = size_calculator.get_total_size(&root_path.join("a"));
+        let b_size = size_calculator.get_total_size(&root_path.join("b"));
+        fs::remove_dir_all(&root_path).unwrap();
+
+        // Whichever path the walk reached first has the file, the other link is free.
+        assert_eq!(
+            root_size,
+            root_directory_size + a_directory_size + b_directory_size + 1000
+        );
+        assert_eq!(a_size + b_size, a_directory_size + b_directory_size + 1000);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(1_234_567, SizeUnits::Bytes), "1234567");
+        assert_eq!(format_size(1000, SizeUnits::Iec), "1000");
+        assert_eq!(format_size(4096, SizeUnits::Iec), "4.0K");
+        assert_eq!(format_size(4096, SizeUnits::Si), "4.1k");
+        assert_eq!(format_size(15 * 1024 * 1024, SizeUnits::Iec), "15M");
+        assert_eq!(format_size(3 * 1024_u64.pow(4), SizeUnits::Iec), "3.0T");
+    }
+
+    #[test]
+    fn test_format_size_with_unit() {
+        assert_eq!(format_size_with_unit(512, SizeUnits::Iec), "512 B");
+        assert_eq!(
+            format_size_with_unit(1_395_864_371, SizeUnits::Iec),
+            "1.3 GiB"
+        );
+        assert_eq!(format_size_with_unit(4096, SizeUnits::Si), "4.1 kB");
+        assert_eq!(format_size_with_unit(12, SizeUnits::Bytes), "12 B");
+    }
+}
@@ -0,0 +1,258 @@
+use std::cmp::Ordering;
+use std::fs;
+use std::time::SystemTime;
+
+/// What the entries of a directory are ordered by.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SortKey {
+    /// Natural order, so `file2` comes before `file10`.
+    Name,
+    /// The text after the last `.`, ties are broken by name.
+    Extension,
+    /// Largest first, ties are broken by name.
+    Size,
+    /// Largest first, counting everything inside of directories, ties are broken by name.
+    Total,
+    /// Oldest modification time first, ties are broken by name.
+    Mtime,
+    /// Directories, files, symlinks and then everything else, ties are broken by name.
+    Type,
+    /// Whatever order the file system hands out.
+    None,
+}
+
+impl SortKey {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "name" | "version" => Some(Self::Name),
+            "ext" | "extension" => Some(Self::Extension),
+            "size" => Some(Self::Size),
+            "total" | "du" => Some(Self::Total),
+            "mtime" | "time" => Some(Self::Mtime),
+            "type" => Some(Self::Type),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// Whether directories are grouped before or after the files next to them.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DirOrder {
+    Mixed,
+    DirsFirst,
+    FilesFirst,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct SortOptions {
+    pub key: SortKey,
+    pub reverse: bool,
+    pub dir_order: DirOrder,
+}
+
+impl SortOptions {
+    pub fn new() -> Self {
+        Self {
+            key: SortKey::Name,
+            reverse: false,
+            dir_order: DirOrder::Mixed,
+        }
+    }
+}
+
+/// Everything the comparisons need, read once per entry instead of once per comparison.
+struct SortItem {
+    entry: fs::DirEntry,
+    name: String,
+    is_dir: bool,
+    type_rank: u8,
+    size: u64,
+    total_size: u64,
+    mtime: Option<SystemTime>,
+}
+
+impl SortItem {
+    fn new(entry: fs::DirEntry, total_size: u64) -> Self {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let file_type = entry.file_type().ok();
+        let is_dir = file_type.is_some_and(|file_type| file_type.is_dir());
+        let type_rank = match file_type {
+            Some(file_type) if file_type.is_dir() => 0,
+            Some(file_type) if file_type.is_file() => 1,
+            Some(file_type) if file_type.is_symlink() => 2,
+            _ => 3,
+        };
+        let metadata = entry.metadata().ok();
+
+        Self {
+            entry,
+            name,
+            is_dir,
+            type_rank,
+            size: metadata.as_ref().map_or(0, |metadata| metadata.len()),
+            total_size,
+            mtime: metadata.and_then(|metadata| metadata.modified().ok()),
+        }
+    }
+}
+
+/// Orders the entries of a single directory in place, `get_total_size` is only asked for
+/// the sizes with `--sort total`.
+pub fn sort_entries(
+    entries: &mut Vec<fs::DirEntry>,
+    options: &SortOptions,
+    get_total_size: &mut dyn FnMut(&fs::DirEntry) -> u64,
+) {
+    if options.key == SortKey::None && options.dir_order == DirOrder::Mixed {
+        return;
+    }
+
+    let mut items: Vec<SortItem> = entries
+        .drain(..)
+        .map(|entry| {
+            let total_size = match options.key {
+                SortKey::Total => get_total_size(&entry),
+                _ => 0,
+            };
+            SortItem::new(entry, total_size)
+        })
+        .collect();
+
+    items.sort_by(|a, b| {
+        let group = match options.dir_order {
+            DirOrder::Mixed => Ordering::Equal,
+            DirOrder::DirsFirst => b.is_dir.cmp(&a.is_dir),
+            DirOrder::FilesFirst => a.is_dir.cmp(&b.is_dir),
+        };
+
+        let ordering = compare_by_key(a, b, options.key);
+        let ordering = if options.reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+
+        group.then(ordering)
+    });
+
+    entries.extend(items.into_iter().map(|item| item.entry));
+}
+
+fn compare_by_key(a: &SortItem, b: &SortItem, key: SortKey) -> Ordering {
+    match key {
+        SortKey::Name => natural_cmp(&a.name, &b.name),
+        SortKey::Extension => natural_cmp(extension(&a.name), extension(&b.name))
+            .then_with(|| natural_cmp(&a.name, &b.name)),
+        SortKey::Size => b
+            .size
+            .cmp(&a.size)
+            .then_with(|| natural_cmp(&a.name, &b.name)),
+        SortKey::Total => b
+            .total_size
+            .cmp(&a.total_size)
+            .then_with(|| natural_cmp(&a.name, &b.name)),
+        SortKey::Mtime => a
+            .mtime
+            .cmp(&b.mtime)
+            .then_with(|| natural_cmp(&a.name, &b.name)),
+        SortKey::Type => a
+            .type_rank
+            .cmp(&b.type_rank)
+            .then_with(|| natural_cmp(&a.name, &b.name)),
+        SortKey::None => Ordering::Equal,
+    }
+}
+
+/// The extension of a file name, empty for names without one and for dotfiles like `.gitignore`.
+fn extension(name: &str) -> &str {
+    match name.rfind('.') {
+        Some(0) | None => "",
+        Some(index) => &name[index + 1..],
+    }
+}
+
+/// Compares names the way a person would, runs of digits are compared by their value
+/// so `v1.9` comes before `v1.10`. Letters are compared case-insensitively first, and
+/// the raw text breaks any remaining ties so the order is always the same.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(a_char), Some(b_char)) if a_char.is_ascii_digit() && b_char.is_ascii_digit() => {
+                let a_number = take_digits(&mut a_chars);
+                let b_number = take_digits(&mut b_chars);
+
+                // Leading zeros don't change the value, so compare without them.
+                let a_trimmed = a_number.trim_start_matches('0');
+                let b_trimmed = b_number.trim_start_matches('0');
+
+                let ordering = a_trimmed
+                    .len()
+                    .cmp(&b_trimmed.len())
+                    .then_with(|| a_trimmed.cmp(b_trimmed));
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(a_char), Some(b_char)) => {
+                let ordering = a_char.to_lowercase().cmp(b_char.to_lowercase());
+
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+
+                a_chars.next();
+                b_chars.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>) -> String {
+    let mut digits = String::new();
+
+    while let Some(&digit) = chars.peek() {
+        if !digit.is_ascii_digit() {
+            break;
+        }
+        digits.push(digit);
+        chars.next();
+    }
+
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp_numbers() {
+        assert_eq!(natural_cmp("file2", "file10"), Ordering::Less);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(natural_cmp("a007", "a7"), Ordering::Less);
+        assert_eq!(natural_cmp("file", "file1"), Ordering::Less);
+    }
+
+    #[test]
+    fn test_natural_cmp_case() {
+        assert_eq!(natural_cmp("apple", "Banana"), Ordering::Less);
+        assert_eq!(natural_cmp("README", "readme"), Ordering::Less);
+        assert_eq!(natural_cmp("same", "same"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(extension("main.rs"), "rs");
+        assert_eq!(extension("archive.tar.gz"), "gz");
+        assert_eq!(extension(".gitignore"), "");
+        assert_eq!(extension("Makefile"), "");
+    }
+}
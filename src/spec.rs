@@ -0,0 +1,914 @@
+//! `mtree spec` writes a BSD mtree(8) specification of the base directory, the same format
+//! as `mtree -c`, to keep as a manifest of what the directory should look like.
+//!
+//! The entries of a directory come right after it, files first, then each sub directory
+//! followed by a `..` line to return to its parent. Values shared by most files of a
+//! directory are written once in a `/set` line and only the differences after that.
+//!
+//! `parse_spec` reads such a file back for `mtree verify`.
+use super::config::Config;
+use super::filter::{EntryFilter, FilterOptions};
+use super::hash::{hash_file, HashAlgorithm, HashStats};
+#[cfg(unix)]
+use super::users::NameTable;
+use super::walk_error::{ErrorTally, WalkError};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, Metadata};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+/// Names are padded to this width so the keywords line up, like `mtree -c`.
+const NAME_WIDTH: usize = 16;
+
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub enum SpecKeyword {
+    Type,
+    Uid,
+    Gid,
+    Mode,
+    Nlink,
+    Size,
+    Link,
+    Time,
+    Md5Digest,
+    Sha256Digest,
+    Blake3Digest,
+}
+
+impl SpecKeyword {
+    /// Every keyword, in the order they are written.
+    pub const ALL: [Self; 11] = [
+        Self::Type,
+        Self::Uid,
+        Self::Gid,
+        Self::Mode,
+        Self::Nlink,
+        Self::Size,
+        Self::Link,
+        Self::Time,
+        Self::Md5Digest,
+        Self::Sha256Digest,
+        Self::Blake3Digest,
+    ];
+
+    /// The keywords written without `-k`, reading every file for a digest is left to `-k`.
+    pub const DEFAULT: [Self; 8] = [
+        Self::Type,
+        Self::Uid,
+        Self::Gid,
+        Self::Mode,
+        Self::Nlink,
+        Self::Size,
+        Self::Link,
+        Self::Time,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|keyword| keyword.get_name() == name)
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Type => "type",
+            Self::Uid => "uid",
+            Self::Gid => "gid",
+            Self::Mode => "mode",
+            Self::Nlink => "nlink",
+            Self::Size => "size",
+            Self::Link => "link",
+            Self::Time => "time",
+            Self::Md5Digest => "md5digest",
+            Self::Sha256Digest => "sha256digest",
+            Self::Blake3Digest => "blake3digest",
+        }
+    }
+
+    /// The algorithm of the digest keywords.
+    pub fn get_hash_algorithm(&self) -> Option<HashAlgorithm> {
+        match self {
+            Self::Md5Digest => Some(HashAlgorithm::Md5),
+            Self::Sha256Digest => Some(HashAlgorithm::Sha256),
+            Self::Blake3Digest => Some(HashAlgorithm::Blake3),
+            _ => None,
+        }
+    }
+
+    /// Parses the value of `-k`, a list of keywords separated by commas or spaces.
+    pub fn parse_list(list: &str) -> Result<Vec<Self>, String> {
+        let mut keywords = Vec::new();
+
+        for name in list.split([',', ' ']).filter(|name| !name.is_empty()) {
+            let keyword =
+                Self::from_name(name).ok_or_else(|| format!("Unknown spec keyword `{name}`."))?;
+            keywords.push(keyword);
+        }
+
+        if keywords.is_empty() {
+            return Err("No spec keywords given.".to_string());
+        }
+
+        keywords.sort();
+        keywords.dedup();
+        Ok(keywords)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SpecType {
+    File,
+    Dir,
+    Link,
+    Block,
+    Char,
+    Fifo,
+    Socket,
+}
+
+impl SpecType {
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::File => "file",
+            Self::Dir => "dir",
+            Self::Link => "link",
+            Self::Block => "block",
+            Self::Char => "char",
+            Self::Fifo => "fifo",
+            Self::Socket => "socket",
+        }
+    }
+
+    #[cfg(unix)]
+    fn from_metadata(metadata: &Metadata) -> Self {
+        use std::os::unix::fs::FileTypeExt;
+
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            Self::Dir
+        } else if file_type.is_symlink() {
+            Self::Link
+        } else if file_type.is_block_device() {
+            Self::Block
+        } else if file_type.is_char_device() {
+            Self::Char
+        } else if file_type.is_fifo() {
+            Self::Fifo
+        } else if file_type.is_socket() {
+            Self::Socket
+        } else {
+            Self::File
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn from_metadata(metadata: &Metadata) -> Self {
+        let file_type = metadata.file_type();
+        if file_type.is_dir() {
+            Self::Dir
+        } else if file_type.is_symlink() {
+            Self::Link
+        } else {
+            Self::File
+        }
+    }
+}
+
+/// What the spec records about a single entry. The owner, mode and link count are only
+/// known on unix.
+#[derive(Debug, PartialEq, Clone)]
+pub struct EntryStat {
+    pub path: PathBuf,
+    pub spec_type: SpecType,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    pub mode: Option<u32>,
+    pub nlink: Option<u64>,
+    pub size: u64,
+    /// The target of a symlink.
+    pub link: Option<PathBuf>,
+    /// The modification time as seconds and nanoseconds since the epoch.
+    pub time: Option<(u64, u32)>,
+}
+
+impl EntryStat {
+    /// Reads the entry at path without following symlinks.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let metadata = fs::symlink_metadata(path)?;
+        let spec_type = SpecType::from_metadata(&metadata);
+
+        let link = match spec_type {
+            SpecType::Link => Some(fs::read_link(path)?),
+            _ => None,
+        };
+
+        let time = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| (duration.as_secs(), duration.subsec_nanos()));
+
+        let (uid, gid, mode, nlink) = get_owner_and_mode(&metadata);
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            spec_type,
+            uid,
+            gid,
+            mode,
+            nlink,
+            size: metadata.len(),
+            link,
+            time,
+        })
+    }
+
+    /// The value of a keyword the way it is written in a spec, `None` if it doesn't apply.
+    /// Digests need the contents of the file, see `get_digest`.
+    pub fn get_value(&self, keyword: SpecKeyword) -> Option<String> {
+        match keyword {
+            SpecKeyword::Type => Some(self.spec_type.get_name().to_string()),
+            SpecKeyword::Uid => self.uid.map(|uid| uid.to_string()),
+            SpecKeyword::Gid => self.gid.map(|gid| gid.to_string()),
+            SpecKeyword::Mode => self.mode.map(format_mode),
+            SpecKeyword::Nlink => self.nlink.map(|nlink| nlink.to_string()),
+            // Only the size of regular files means anything across machines.
+            SpecKeyword::Size => (self.spec_type == SpecType::File).then(|| self.size.to_string()),
+            SpecKeyword::Link => self
+                .link
+                .as_ref()
+                .map(|link| encode_name(link.as_os_str().as_encoded_bytes())),
+            SpecKeyword::Time => self
+                .time
+                .map(|(seconds, nanos)| format!("{seconds}.{nanos:09}")),
+            SpecKeyword::Md5Digest | SpecKeyword::Sha256Digest | SpecKeyword::Blake3Digest => None,
+        }
+    }
+
+    /// The hex digest of a regular file, other types have none.
+    pub fn get_digest(
+        &self,
+        hash_algorithm: HashAlgorithm,
+        hash_stats: &mut HashStats,
+    ) -> Option<io::Result<String>> {
+        (self.spec_type == SpecType::File)
+            .then(|| hash_file(&self.path, hash_algorithm, hash_stats))
+    }
+}
+
+#[cfg(unix)]
+fn get_owner_and_mode(metadata: &Metadata) -> (Option<u32>, Option<u32>, Option<u32>, Option<u64>) {
+    use std::os::unix::fs::MetadataExt;
+
+    (
+        Some(metadata.uid()),
+        Some(metadata.gid()),
+        Some(metadata.mode() & 0o7777),
+        Some(metadata.nlink()),
+    )
+}
+
+#[cfg(not(unix))]
+fn get_owner_and_mode(
+    _metadata: &Metadata,
+) -> (Option<u32>, Option<u32>, Option<u32>, Option<u64>) {
+    (None, None, None, None)
+}
+
+/// `0644`, `04755`, with the leading zero of `%#o`.
+pub fn format_mode(mode: u32) -> String {
+    format!("0{mode:o}")
+}
+
+/// The keywords a `/set` line can give a default value for.
+const SET_KEYWORDS: [SpecKeyword; 5] = [
+    SpecKeyword::Type,
+    SpecKeyword::Uid,
+    SpecKeyword::Gid,
+    SpecKeyword::Mode,
+    SpecKeyword::Nlink,
+];
+
+struct SpecWriter<'a> {
+    writer: &'a mut dyn Write,
+    keywords: Vec<SpecKeyword>,
+    entry_filter: EntryFilter,
+    /// The values of the last `/set` line.
+    defaults: HashMap<SpecKeyword, String>,
+    hash_stats: HashStats,
+    errors: ErrorTally,
+}
+
+impl SpecWriter<'_> {
+    /// Writes a directory, its entries, and then its sub directories. The root is named `.`.
+    ///
+    /// Anything that can't be read is left out with a `# unable to read` comment, like
+    /// `mtree -c`, and counted in the errors.
+    fn write_directory(&mut self, path: &Path, name: &str, relative_path: &str) -> io::Result<()> {
+        writeln!(self.writer, "\n# {relative_path}")?;
+
+        let stat = match EntryStat::read(path) {
+            Ok(stat) => stat,
+            Err(error) => return self.write_error(relative_path, &error),
+        };
+        let mut files: Vec<(String, EntryStat)> = Vec::new();
+        let mut dirs: Vec<(String, PathBuf)> = Vec::new();
+
+        match fs::read_dir(path) {
+            Ok(read_dir) => {
+                self.entry_filter.enter_directory(path);
+
+                for entry in read_dir {
+                    let entry = match entry {
+                        Ok(entry) => entry,
+                        Err(error) => {
+                            self.write_error(relative_path, &error)?;
+                            continue;
+                        }
+                    };
+                    let entry_path = entry.path();
+                    let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+
+                    if !self.entry_filter.is_visible(&entry_path, is_dir) {
+                        continue;
+                    }
+
+                    let entry_name = encode_name(entry.file_name().as_encoded_bytes());
+                    if is_dir {
+                        dirs.push((entry_name, entry_path));
+                        continue;
+                    }
+
+                    match EntryStat::read(&entry_path) {
+                        Ok(entry_stat) => files.push((entry_name, entry_stat)),
+                        Err(error) => {
+                            self.write_error(&format!("{relative_path}/{entry_name}"), &error)?
+                        }
+                    }
+                }
+
+                self.entry_filter.leave_directory();
+            }
+            Err(error) => self.write_error(relative_path, &error)?,
+        }
+
+        files.sort_by(|(a, _), (b, _)| a.cmp(b));
+        dirs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        self.write_set(&files)?;
+        self.write_entry(name, &stat, true)?;
+
+        for (file_name, file_stat) in &files {
+            self.write_entry(file_name, file_stat, false)?;
+        }
+
+        for (dir_name, dir_path) in dirs {
+            self.write_directory(&dir_path, &dir_name, &format!("{relative_path}/{dir_name}"))?;
+        }
+
+        if name != "." {
+            writeln!(self.writer, "# {relative_path}")?;
+        }
+        writeln!(self.writer, "..\n")
+    }
+
+    fn write_error(&mut self, relative_path: &str, error: &io::Error) -> io::Result<()> {
+        self.errors.add(WalkError::from_io_error(error));
+        writeln!(self.writer, "# unable to read {relative_path}: {error}")
+    }
+
+    /// Writes a `/set` line with the most common values of the files, when they differ from
+    /// the last one.
+    fn write_set(&mut self, files: &[(String, EntryStat)]) -> io::Result<()> {
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let mut defaults = HashMap::new();
+
+        for keyword in SET_KEYWORDS {
+            if !self.keywords.contains(&keyword) {
+                continue;
+            }
+
+            let mut counts: HashMap<String, usize> = HashMap::new();
+            for (_, stat) in files {
+                if let Some(value) = stat.get_value(keyword) {
+                    *counts.entry(value).or_default() += 1;
+                }
+            }
+
+            // Ties go to the smallest value, so the same tree always gives the same spec.
+            if let Some((value, _)) = counts
+                .into_iter()
+                .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+            {
+                defaults.insert(keyword, value);
+            }
+        }
+
+        if defaults == self.defaults {
+            return Ok(());
+        }
+
+        write!(self.writer, "/set")?;
+        for keyword in SET_KEYWORDS {
+            if let Some(value) = defaults.get(&keyword) {
+                write!(self.writer, " {}={value}", keyword.get_name())?;
+            }
+        }
+        writeln!(self.writer)?;
+
+        self.defaults = defaults;
+        Ok(())
+    }
+
+    fn write_entry(&mut self, name: &str, stat: &EntryStat, is_dir: bool) -> io::Result<()> {
+        let mut line = if is_dir {
+            name.to_string()
+        } else {
+            format!("    {name}")
+        };
+
+        // Keep at least one space between the name and the first keyword.
+        line.push(' ');
+        while line.len() < NAME_WIDTH {
+            line.push(' ');
+        }
+
+        let mut values = Vec::new();
+        for &keyword in &self.keywords {
+            let value = match keyword.get_hash_algorithm() {
+                // A file that can't be read is written without its digest, like `mtree -c`.
+                Some(hash_algorithm) => stat
+                    .get_digest(hash_algorithm, &mut self.hash_stats)
+                    .and_then(Result::ok),
+                None => stat.get_value(keyword),
+            };
+            let Some(value) = value else {
+                continue;
+            };
+
+            if self.defaults.get(&keyword) == Some(&value) {
+                continue;
+            }
+
+            values.push(format!("{}={value}", keyword.get_name()));
+        }
+
+        line.push_str(&values.join(" "));
+        writeln!(self.writer, "{}", line.trim_end())
+    }
+}
+
+/// Gives what was hashed for the digest keywords, for the verbose report, and the errors
+/// the walk ran into.
+pub fn write_spec(config: &Config, writer: &mut dyn Write) -> io::Result<(HashStats, ErrorTally)> {
+    let root_dir = config
+        .get_root_dir()
+        .expect("Config should be in spec mode.");
+    let root_path = PathBuf::from(&root_dir);
+    let tree = fs::canonicalize(&root_path).unwrap_or_else(|_| root_path.clone());
+
+    let filter_options = config
+        .get_filter_options()
+        .expect("Config should be in spec mode.");
+
+    writeln!(writer, "#\t   user: {}", get_user_name())?;
+    writeln!(writer, "#\tmachine: {}", get_machine_name())?;
+    writeln!(writer, "#\t   tree: {}", tree.display())?;
+    writeln!(
+        writer,
+        "#\t   date: {}",
+        chrono::Local::now().format("%a %b %e %H:%M:%S %Y")
+    )?;
+    // Whatever the filters leave out isn't checked by `mtree verify` either, so say so.
+    if let Some(filters) = format_filters(&filter_options) {
+        writeln!(writer, "#\t filter: {filters}")?;
+    }
+
+    let mut spec_writer = SpecWriter {
+        writer,
+        keywords: config
+            .get_spec_keywords()
+            .expect("Config should be in spec mode."),
+        entry_filter: EntryFilter::new(filter_options, root_path.clone()),
+        defaults: HashMap::new(),
+        hash_stats: HashStats::default(),
+        errors: ErrorTally::default(),
+    };
+
+    spec_writer.write_directory(&root_path, ".", ".")?;
+    Ok((spec_writer.hash_stats, spec_writer.errors))
+}
+
+/// `-I *.o -P *.rs --gitignore`, the options that left entries out of the spec.
+fn format_filters(filter_options: &FilterOptions) -> Option<String> {
+    let mut filters: Vec<String> = Vec::new();
+    for (tag, globs) in [("-I", &filter_options.ignore), ("-P", &filter_options.only)] {
+        filters.extend(
+            globs
+                .iter()
+                .map(|glob| format!("{tag} {}", glob.get_pattern())),
+        );
+    }
+    if filter_options.use_gitignore {
+        filters.push("--gitignore".to_string());
+    }
+
+    (!filters.is_empty()).then(|| filters.join(" "))
+}
+
+/// The name of the user running mtree, looked up by id so the environment can't change it.
+#[cfg(unix)]
+fn get_user_name() -> String {
+    extern "C" {
+        fn getuid() -> u32;
+    }
+
+    // SAFETY: `getuid` has no preconditions and can't fail.
+    let uid = unsafe { getuid() };
+    NameTable::read("/etc/passwd").get_name(uid)
+}
+
+/// Without user ids, the name of the account is all there is.
+#[cfg(not(unix))]
+fn get_user_name() -> String {
+    env::var("USERNAME").unwrap_or_else(|_| "unknown".to_string())
+}
+
+fn get_machine_name() -> String {
+    fs::read_to_string("/etc/hostname")
+        .map(|name| name.trim().to_string())
+        .ok()
+        .filter(|name| !name.is_empty())
+        .or_else(|| env::var("HOSTNAME").ok())
+        .or_else(|| env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Escapes a name like `strsvis(3)` with `VIS_WHITE | VIS_OCTAL | VIS_GLOB`: whitespace,
+/// anything that isn't printable ASCII and the glob characters `*?[#` become `\ooo`.
+pub fn encode_name(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+
+    for &byte in bytes {
+        match byte {
+            b'\\' => encoded.push_str("\\\\"),
+            b'*' | b'?' | b'[' | b'#' => encoded.push_str(&format!("\\{byte:03o}")),
+            b'!'..=b'~' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("\\{byte:03o}")),
+        }
+    }
+
+    encoded
+}
+
+/// An entry of a parsed spec, with the `/set` defaults already filled in.
+#[derive(Debug, PartialEq)]
+pub struct SpecEntry {
+    /// Relative to the base directory, empty for the base directory itself.
+    pub path: PathBuf,
+    /// The values as written in the spec, keywords mtree doesn't know about are left out.
+    pub values: Vec<(SpecKeyword, String)>,
+    /// Given the `ignore` keyword, nothing below this directory is checked.
+    pub is_ignored: bool,
+    /// Given the `optional` keyword, it's fine if this entry doesn't exist.
+    pub is_optional: bool,
+}
+
+impl SpecEntry {
+    pub fn get_value(&self, keyword: SpecKeyword) -> Option<&str> {
+        self.values
+            .iter()
+            .find(|(entry_keyword, _)| *entry_keyword == keyword)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.get_value(SpecKeyword::Type) == Some("dir")
+    }
+
+    /// `.`, or the path starting with `./` like in the comments of a spec.
+    pub fn get_display_path(&self) -> String {
+        get_display_path(&self.path)
+    }
+}
+
+/// Parses a spec in the format of `mtree -c`, or with full paths like `mtree -C`.
+pub fn parse_spec(contents: &str) -> Result<Vec<SpecEntry>, String> {
+    let mut entries = Vec::new();
+    let mut defaults: Vec<(String, Option<String>)> = Vec::new();
+    // The directories entered by lines with `type=dir`, left again by `..`.
+    let mut directories: Vec<PathBuf> = Vec::new();
+    let mut lines = contents.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+
+        // A line ending in an unescaped `\` continues on the next one.
+        let mut line = line.to_string();
+        while line.ends_with('\\') && (line.len() - line.trim_end_matches('\\').len()) % 2 == 1 {
+            line.pop();
+            match lines.next() {
+                Some((_, next_line)) => line.push_str(next_line),
+                None => break,
+            }
+        }
+
+        let mut words = line.split_whitespace();
+        let Some(name) = words.next() else {
+            continue;
+        };
+
+        if name.starts_with('#') {
+            continue;
+        }
+
+        match name {
+            "/set" => {
+                for (keyword, value) in words.map(split_keyword) {
+                    defaults.retain(|(default, _)| *default != keyword);
+                    defaults.push((keyword, value));
+                }
+                continue;
+            }
+            "/unset" => {
+                for (keyword, _) in words.map(split_keyword) {
+                    if keyword == "all" {
+                        defaults.clear();
+                    }
+                    defaults.retain(|(default, _)| *default != keyword);
+                }
+                continue;
+            }
+            ".." => {
+                if directories.pop().is_none() {
+                    return Err(format!(
+                        "Line {line_number}: `..` above the base directory."
+                    ));
+                }
+                continue;
+            }
+            _ if name.starts_with('/') => {
+                return Err(format!("Line {line_number}: unknown command `{name}`."));
+            }
+            _ => (),
+        }
+
+        let mut keywords = defaults.clone();
+        for (keyword, value) in words.map(split_keyword) {
+            keywords.retain(|(existing, _)| *existing != keyword);
+            keywords.push((keyword, value));
+        }
+
+        let mut values = Vec::new();
+        for (keyword, value) in &keywords {
+            match (SpecKeyword::from_name(keyword), value) {
+                (Some(keyword), Some(value)) => values.push((keyword, value.clone())),
+                (Some(keyword), None) => {
+                    return Err(format!(
+                        "Line {line_number}: `{}` needs a value.",
+                        keyword.get_name()
+                    ))
+                }
+                (None, _) => (),
+            }
+        }
+        values.sort();
+
+        let has_keyword = |name: &str| keywords.iter().any(|(keyword, _)| keyword == name);
+        let name_path = bytes_to_path(decode_name_bytes(name));
+
+        // Names with a `/` are full paths and don't change the directory.
+        let is_full_path = name.contains('/');
+        let path = if is_full_path {
+            name_path
+                .strip_prefix(".")
+                .unwrap_or(&name_path)
+                .to_path_buf()
+        } else {
+            let current_directory = directories.last().cloned().unwrap_or_default();
+            if name == "." {
+                current_directory
+            } else {
+                current_directory.join(name_path)
+            }
+        };
+
+        let entry = SpecEntry {
+            path,
+            values,
+            is_ignored: has_keyword("ignore"),
+            is_optional: has_keyword("optional"),
+        };
+
+        if entry.is_dir() && !is_full_path {
+            directories.push(entry.path.clone());
+        }
+
+        entries.push(entry);
+    }
+
+    Ok(entries)
+}
+
+/// `size=12` gives the keyword and value, a lone `ignore` gives no value.
+/// `.` for the base directory, or the path relative to it starting with `./`, with every
+/// name encoded like in a spec so `\` and white space can't be mistaken for anything else.
+pub fn get_display_path(relative_path: &Path) -> String {
+    let mut display_path = ".".to_string();
+
+    for component in relative_path.components() {
+        display_path.push('/');
+        display_path.push_str(&encode_name(component.as_os_str().as_encoded_bytes()));
+    }
+
+    display_path
+}
+
+fn split_keyword(word: &str) -> (String, Option<String>) {
+    match word.split_once('=') {
+        Some((keyword, value)) => (keyword.to_string(), Some(value.to_string())),
+        None => (word.to_string(), None),
+    }
+}
+
+#[cfg(unix)]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::OsStringExt;
+
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn bytes_to_path(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Reverses `encode_name`, also accepting the named escapes of `vis(3)` like `\\s`.
+pub fn decode_name_bytes(encoded: &str) -> Vec<u8> {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let byte = bytes[index];
+        index += 1;
+
+        if byte != b'\\' || index == bytes.len() {
+            decoded.push(byte);
+            continue;
+        }
+
+        let escaped = bytes[index];
+        index += 1;
+
+        match escaped {
+            b'0'..=b'7' => {
+                let mut value = u32::from(escaped - b'0');
+                for _ in 0..2 {
+                    match bytes.get(index) {
+                        Some(digit @ b'0'..=b'7') => {
+                            value = value * 8 + u32::from(digit - b'0');
+                            index += 1;
+                        }
+                        _ => break,
+                    }
+                }
+                decoded.push(value as u8);
+            }
+            b's' => decoded.push(b' '),
+            b't' => decoded.push(b'\t'),
+            b'n' => decoded.push(b'\n'),
+            b'r' => decoded.push(b'\r'),
+            escaped => decoded.push(escaped),
+        }
+    }
+
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glob::Glob;
+
+    #[test]
+    fn test_encode_name() {
+        assert_eq!(encode_name(b"main.rs"), "main.rs");
+        assert_eq!(encode_name(b"my file"), "my\\040file");
+        assert_eq!(encode_name(b"a*b#c\\d"), "a\\052b\\043c\\\\d");
+        assert_eq!(encode_name(&[b'a', 0xff]), "a\\377");
+    }
+
+    #[test]
+    fn test_get_display_path() {
+        assert_eq!(get_display_path(Path::new("")), ".");
+        assert_eq!(
+            get_display_path(Path::new("sub/my file")),
+            "./sub/my\\040file"
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            get_display_path(Path::new("back\\slash")),
+            "./back\\\\slash"
+        );
+    }
+
+    #[test]
+    fn test_format_filters() {
+        assert_eq!(format_filters(&FilterOptions::new()), None);
+
+        let filter_options = FilterOptions {
+            ignore: vec![Glob::new("*.o").unwrap()],
+            use_gitignore: true,
+            ..FilterOptions::new()
+        };
+        assert_eq!(
+            format_filters(&filter_options),
+            Some("-I *.o --gitignore".to_string())
+        );
+    }
+
+    #[test]
+    fn test_decode_name() {
+        for name in ["main.rs", "my file", "a*b#c\\d", "ü"] {
+            assert_eq!(
+                decode_name_bytes(&encode_name(name.as_bytes())),
+                name.as_bytes()
+            );
+        }
+        assert_eq!(decode_name_bytes("a\\sb"), b"a b");
+        assert_eq!(decode_name_bytes("a\\377"), [b'a', 0xff]);
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let contents = "#\t   user: root
+
+# .
+/set type=file uid=0 mode=0644
+.               type=dir mode=0755
+    a\\040b.txt  size=6 flags=none
+    link        type=link link=a.txt
+
+# ./sub
+sub             type=dir ignore \\
+                mode=0700
+    c           optional
+# ./sub
+..
+
+./full/path     size=1
+..
+";
+        let entries = parse_spec(contents).unwrap();
+        let paths: Vec<String> = entries.iter().map(SpecEntry::get_display_path).collect();
+        assert_eq!(
+            paths,
+            [
+                ".",
+                "./a\\040b.txt",
+                "./link",
+                "./sub",
+                "./sub/c",
+                "./full/path"
+            ]
+        );
+
+        assert!(entries[0].is_dir());
+        assert_eq!(entries[0].get_value(SpecKeyword::Mode), Some("0755"));
+        assert_eq!(entries[1].get_value(SpecKeyword::Type), Some("file"));
+        assert_eq!(entries[1].get_value(SpecKeyword::Uid), Some("0"));
+        assert_eq!(entries[1].get_value(SpecKeyword::Size), Some("6"));
+        assert_eq!(entries[2].get_value(SpecKeyword::Link), Some("a.txt"));
+        assert!(entries[3].is_ignored);
+        assert_eq!(entries[3].get_value(SpecKeyword::Mode), Some("0700"));
+        assert!(entries[4].is_optional);
+    }
+
+    #[test]
+    fn test_parse_spec_invalid() {
+        assert!(parse_spec(". type=dir\n..\n..\n").is_err());
+        assert!(parse_spec("/frobnicate\n").is_err());
+        assert!(parse_spec("a size\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_keyword_list() {
+        assert_eq!(
+            SpecKeyword::parse_list("time,type size"),
+            Ok(vec![
+                SpecKeyword::Type,
+                SpecKeyword::Size,
+                SpecKeyword::Time
+            ])
+        );
+        assert!(SpecKeyword::parse_list("type,flags").is_err());
+        assert!(SpecKeyword::parse_list(",").is_err());
+    }
+}
@@ -0,0 +1,249 @@
+// Keeps the generated trees in a file like README.md up to date. Every region between a
+// start and an end marker is replaced with a fresh render, using the flags in its start marker:
+//
+// <!-- mtree:start -D 2 -I target -->
+// ...replaced...
+// <!-- mtree:end -->
+use super::config::Config;
+use super::output::OutputFormat;
+use super::render::Renderer;
+use super::utils::SharedBuffer;
+use super::walk_error::{ErrorTally, WalkError};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub const START_MARKER: &str = "<!-- mtree:start";
+pub const END_MARKER: &str = "<!-- mtree:end -->";
+
+/// Why the file couldn't be updated, which decides the exit code.
+#[derive(Debug)]
+pub enum UpdateFileError {
+    /// The markers or the flags in them can't be used.
+    Invalid(String),
+    /// The file couldn't be read or written, or the output of a region failed.
+    Io { message: String, error: WalkError },
+}
+
+impl UpdateFileError {
+    fn from_io_error(message: String, error: &io::Error) -> Self {
+        Self::Io {
+            message: format!("{message}: {error}."),
+            error: WalkError::from_io_error(error),
+        }
+    }
+}
+
+impl fmt::Display for UpdateFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Invalid(message) | Self::Io { message, .. } => write!(f, "{message}"),
+        }
+    }
+}
+
+/// Rewrites the file, or with `is_check` only compares it. Returns whether it was up to date,
+/// and what couldn't be read in the rendered directories.
+pub fn update_file(file: &str, is_check: bool) -> Result<(bool, ErrorTally), UpdateFileError> {
+    let contents = fs::read_to_string(file).map_err(|error| {
+        UpdateFileError::from_io_error(format!("Could not read `{file}`"), &error)
+    })?;
+
+    // Paths in the markers are relative to the file, so render from its directory.
+    let file_dir = Path::new(file)
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let (updated_contents, errors) =
+        update_contents(&contents, file_dir).map_err(|error| match error {
+            UpdateFileError::Invalid(message) => {
+                UpdateFileError::Invalid(format!("In `{file}`: {message}"))
+            }
+            UpdateFileError::Io { message, error } => UpdateFileError::Io {
+                message: format!("In `{file}`: {message}"),
+                error,
+            },
+        })?;
+
+    let is_up_to_date = updated_contents == contents;
+
+    if !is_check && !is_up_to_date {
+        fs::write(file, updated_contents).map_err(|error| {
+            UpdateFileError::from_io_error(format!("Could not write `{file}`"), &error)
+        })?;
+    }
+
+    Ok((is_up_to_date, errors))
+}
+
+/// Replaces the inside of every marked region with a new render of the directories in
+/// file_dir.
+fn update_contents(
+    contents: &str,
+    file_dir: &Path,
+) -> Result<(String, ErrorTally), UpdateFileError> {
+    let invalid = UpdateFileError::Invalid;
+    let mut updated_contents = String::with_capacity(contents.len());
+    let mut errors = ErrorTally::default();
+    let mut rest = contents;
+    let mut region_count = 0;
+
+    while let Some(start) = rest.find(START_MARKER) {
+        let start_line_end = rest[start..]
+            .find('\n')
+            .map(|index| start + index + 1)
+            .ok_or_else(|| {
+                invalid("The start marker needs to be followed by a new line.".to_string())
+            })?;
+        let start_line = &rest[start..start_line_end];
+
+        let flags = start_line
+            .trim_end()
+            .strip_prefix(START_MARKER)
+            .and_then(|flags| flags.strip_suffix("-->"))
+            .ok_or_else(|| {
+                invalid(format!(
+                    "The start marker `{}` is not closed.",
+                    start_line.trim_end()
+                ))
+            })?;
+
+        let end = rest[start_line_end..]
+            .find(END_MARKER)
+            .map(|index| start_line_end + index)
+            .ok_or_else(|| {
+                invalid(format!(
+                    "The start marker `{}` has no end marker.",
+                    start_line.trim_end()
+                ))
+            })?;
+
+        let (region, region_errors) = render_region(flags, file_dir)?;
+        updated_contents.push_str(&rest[..start_line_end]);
+        updated_contents.push_str(&region);
+        errors.add_tally(&region_errors);
+        rest = &rest[end..];
+        region_count += 1;
+    }
+
+    if region_count == 0 {
+        return Err(invalid(format!("There is no `{START_MARKER} -->` marker.")));
+    }
+
+    updated_contents.push_str(rest);
+    Ok((updated_contents, errors))
+}
+
+fn render_region(flags: &str, file_dir: &Path) -> Result<(String, ErrorTally), UpdateFileError> {
+    let invalid = UpdateFileError::Invalid;
+    // The flags can pick the markdown style, but only markdown ends up fenced or listed, so
+    // any other format would be pasted into the file as it is.
+    let mut args = vec![
+        "mtree".to_string(),
+        "--output".to_string(),
+        "markdown".to_string(),
+    ];
+    args.extend(split_flags(flags).map_err(invalid)?);
+
+    let config = Config::from_dir(args, file_dir);
+    if let Some(error) = config.get_error() {
+        return Err(invalid(error.to_string()));
+    }
+    if config.get_root_dir().is_none() {
+        return Err(invalid(format!(
+            "The flags `{}` can't be used in a marker.",
+            flags.trim()
+        )));
+    }
+    if config
+        .get_output_options()
+        .is_some_and(|options| options.format != OutputFormat::Markdown)
+    {
+        return Err(invalid(format!(
+            "Only `--output markdown` can be used in a marker, in `{}`.",
+            flags.trim()
+        )));
+    }
+    // The report says how long the render took, so the region would never be up to date.
+    if config.get_is_verbose() {
+        return Err(invalid(format!(
+            "The tag `-v` can't be used in a marker, in `{}`.",
+            flags.trim()
+        )));
+    }
+
+    let buffer = SharedBuffer::new();
+    let errors = Renderer::render_directory(config, Box::new(buffer.clone())).map_err(|error| {
+        UpdateFileError::from_io_error(format!("Could not render `{}`", flags.trim()), &error)
+    })?;
+
+    Ok((buffer.into_string(), errors))
+}
+
+/// Splits the flags of a marker on white space, keeping quoted parts like `-I 'my docs'` together.
+pub fn split_flags(flags: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote: Option<char> = None;
+
+    for c in flags.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => current.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => args.extend(current.take()),
+            (None, c) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(format!(
+            "Unclosed quote in the marker flags `{}`.",
+            flags.trim()
+        ));
+    }
+
+    args.extend(current);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_flags() {
+        assert_eq!(
+            split_flags(" -D 2  -I target ").unwrap(),
+            vec!["-D", "2", "-I", "target"]
+        );
+        assert_eq!(
+            split_flags("-I 'my docs' -P \"*.rs\"").unwrap(),
+            vec!["-I", "my docs", "-P", "*.rs"]
+        );
+        assert_eq!(split_flags("-I ''").unwrap(), vec!["-I", ""]);
+        assert!(split_flags("-I 'open").is_err());
+    }
+
+    #[test]
+    fn test_update_contents_markers() {
+        let file_dir = Path::new(".");
+        assert!(update_contents("no markers here\n", file_dir).is_err());
+        assert!(update_contents("<!-- mtree:start -D 1 -->\nstale\n", file_dir).is_err());
+        assert!(
+            update_contents("<!-- mtree:start -X -->\n<!-- mtree:end -->\n", file_dir).is_err()
+        );
+        assert!(
+            update_contents("<!-- mtree:start -v -->\n<!-- mtree:end -->\n", file_dir).is_err()
+        );
+        assert!(update_contents(
+            "<!-- mtree:start --output json -->\n<!-- mtree:end -->\n",
+            file_dir
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::fs;
+
+/// The names of users or groups by id, read from `/etc/passwd` or `/etc/group`.
+///
+/// Both files have a line per name with the id as the third field, `name:x:1000:...`.
+/// Users from other sources, like LDAP, aren't in them and are shown by id instead.
+pub struct NameTable {
+    names: HashMap<u32, String>,
+}
+
+impl NameTable {
+    /// An empty table when the file can't be read, every id is then shown as a number.
+    pub fn read(path: &str) -> Self {
+        Self::parse(&fs::read_to_string(path).unwrap_or_default())
+    }
+
+    fn parse(contents: &str) -> Self {
+        let mut names = HashMap::new();
+
+        for line in contents.lines() {
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split(':');
+            let (Some(name), Some(id)) = (fields.next(), fields.nth(1)) else {
+                continue;
+            };
+
+            // The first line for an id wins, the same way the C library looks them up.
+            if let Ok(id) = id.parse::<u32>() {
+                names.entry(id).or_insert_with(|| name.to_string());
+            }
+        }
+
+        Self { names }
+    }
+
+    pub fn get_name(&self, id: u32) -> String {
+        self.names
+            .get(&id)
+            .cloned()
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_names() {
+        let table = NameTable::parse(
+            "# comment\nroot:x:0:0:root:/root:/bin/bash\nkai:x:1000:1000::/home/kai:/bin/sh\nbroken\ntoor:x:0:0::/:/bin/sh\n",
+        );
+        assert_eq!(table.get_name(0), "root");
+        assert_eq!(table.get_name(1000), "kai");
+        assert_eq!(table.get_name(1001), "1001");
+    }
+}
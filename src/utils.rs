@@ -1,3 +1,11 @@
+use std::cell::RefCell;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+#[cfg(not(unix))]
+use std::path::PathBuf;
+use std::rc::Rc;
+
 /// A structure to track a count and enforce a limitimum limit.
 ///
 /// The `Limit` struct keeps track of a count that can be incremented or decremented,
@@ -50,10 +58,6 @@ impl Limit {
         self.count -= 1;
     }
 
-    pub fn reset_count(&mut self) {
-        self.count = 0;
-    }
-
     pub fn is_under_limit(&self) -> bool {
         if let Some(limit) = self.limit {
             self.count < limit
@@ -78,3 +82,127 @@ impl Limit {
         self.limit
     }
 }
+
+/// A `Write` target that can still be read after it was handed off as a `Box<dyn Write>`.
+#[derive(Clone, Default)]
+pub struct SharedBuffer {
+    bytes: Rc<RefCell<Vec<u8>>>,
+}
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn into_string(self) -> String {
+        String::from_utf8_lossy(&self.bytes.borrow()).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// What makes a directory the same one as another, whatever path leads to it.
+#[cfg(unix)]
+pub type DirId = (u64, u64);
+
+#[cfg(unix)]
+pub fn get_dir_id(path: &Path) -> Option<DirId> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+/// Without inodes, the path with every link resolved stands in for one.
+#[cfg(not(unix))]
+pub type DirId = PathBuf;
+
+#[cfg(not(unix))]
+pub fn get_dir_id(path: &Path) -> Option<DirId> {
+    fs::canonicalize(path).ok()
+}
+
+/// The path with `/` between its parts on every platform. Only the separators of the
+/// platform are swapped, a `\` in a unix file name stays part of the name.
+pub fn to_slash_path(path: &Path) -> String {
+    path.components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Formats a count with thousands separators, `1234567` becomes `1,234,567`.
+pub fn format_count(count: usize) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+
+    formatted
+}
+
+pub fn pluralize<'a>(count: usize, singular: &'a str, plural: &'a str) -> &'a str {
+    if count == 1 {
+        singular
+    } else {
+        plural
+    }
+}
+
+/// Formats a count followed by the matching noun, `format_count_of(2, "dir", "dirs")` is `2 dirs`.
+pub fn format_count_of(count: usize, singular: &str, plural: &str) -> String {
+    format!(
+        "{} {}",
+        format_count(count),
+        pluralize(count, singular, plural)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_slash_path() {
+        assert_eq!(to_slash_path(Path::new("")), "");
+        assert_eq!(
+            to_slash_path(Path::new("src/output/json.rs")),
+            "src/output/json.rs"
+        );
+        #[cfg(unix)]
+        assert_eq!(
+            to_slash_path(Path::new("dir/back\\slash")),
+            "dir/back\\slash"
+        );
+        #[cfg(windows)]
+        assert_eq!(to_slash_path(Path::new("dir\\file")), "dir/file");
+    }
+
+    #[test]
+    fn test_format_count() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1234), "1,234");
+        assert_eq!(format_count(1234567), "1,234,567");
+    }
+
+    #[test]
+    fn test_format_count_of() {
+        assert_eq!(format_count_of(1, "dir", "dirs"), "1 dir");
+        assert_eq!(format_count_of(1178, "file", "files"), "1,178 files");
+    }
+}
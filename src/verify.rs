@@ -0,0 +1,421 @@
+//! `mtree verify` compares a directory with a spec written by `mtree spec` or BSD `mtree -c`.
+//!
+//! Every difference is written on its own line, in the order of the spec, followed by
+//! the entries of the directory that the spec doesn't mention:
+//!
+//! ```text
+//! missing: ./etc/motd
+//! ./bin/app: size expected 10 found 12
+//! extra: ./tmp/core
+//! unreadable: ./var/cache (permission denied)
+//! ```
+//!
+//! Only the patterns given with `-I` and `-P` leave entries out, and ignore files with
+//! `--gitignore`. An ignore file in the tree is just another entry to check otherwise.
+use super::config::Config;
+use super::filter::EntryFilter;
+use super::hash::HashStats;
+use super::spec::{
+    decode_name_bytes, encode_name, format_mode, get_display_path, parse_spec, EntryStat,
+    SpecEntry, SpecKeyword,
+};
+use super::utils::format_count_of;
+use super::walk_error::{ErrorTally, WalkError};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// What was found to be different, counted for the verbose report.
+#[derive(Debug, PartialEq, Default)]
+pub struct Drift {
+    pub checked: usize,
+    pub missing: usize,
+    pub extra: usize,
+    pub changed: usize,
+    /// What was read for the digest keywords.
+    pub hash_stats: HashStats,
+    /// Directories that couldn't be walked for extra entries.
+    pub errors: ErrorTally,
+}
+
+impl Drift {
+    pub fn has_drift(&self) -> bool {
+        self.missing + self.extra + self.changed > 0
+    }
+
+    pub fn get_message(&self) -> String {
+        let mut message = format!(
+            "{} checked: {} missing, {} extra, {} changed",
+            format_count_of(self.checked, "entry", "entries"),
+            self.missing,
+            self.extra,
+            self.changed
+        );
+
+        if self.hash_stats.files > 0 {
+            message.push('\n');
+            message.push_str(&self.hash_stats.get_message());
+        }
+        if self.errors.get_count() > 0 {
+            message.push('\n');
+            message.push_str(&self.errors.get_message());
+        }
+
+        message
+    }
+}
+
+/// Checks the base directory against the spec file, or standard input without one.
+pub fn verify_directory(config: &Config, writer: &mut dyn Write) -> Result<Drift, String> {
+    let contents = match config.get_spec_file() {
+        Some(file) => fs::read_to_string(&file)
+            .map_err(|error| format!("Couldn't read the spec `{file}`: {error}"))?,
+        None => {
+            let mut contents = String::new();
+            io::stdin()
+                .read_to_string(&mut contents)
+                .map_err(|error| format!("Couldn't read the spec from standard input: {error}"))?;
+            contents
+        }
+    };
+
+    let entries = parse_spec(&contents)?;
+
+    let root_path = PathBuf::from(
+        config
+            .get_root_dir()
+            .expect("Config should be in verify mode."),
+    );
+    let entry_filter = EntryFilter::new(
+        config
+            .get_filter_options()
+            .expect("Config should be in verify mode."),
+        root_path.clone(),
+    );
+
+    let mut verifier = Verifier {
+        writer,
+        root_path,
+        entry_filter,
+        expected_paths: HashSet::new(),
+        ignored_paths: HashSet::new(),
+        drift: Drift::default(),
+    };
+
+    let root_path = verifier.root_path.clone();
+    verifier
+        .check_entries(&entries)
+        .and_then(|_| verifier.find_extra_entries(&root_path))
+        .map_err(|error| error.to_string())?;
+
+    Ok(verifier.drift)
+}
+
+struct Verifier<'a> {
+    writer: &'a mut dyn Write,
+    root_path: PathBuf,
+    entry_filter: EntryFilter,
+    /// Every path in the spec, relative to the base directory.
+    expected_paths: HashSet<PathBuf>,
+    /// Directories given the `ignore` keyword.
+    ignored_paths: HashSet<PathBuf>,
+    drift: Drift,
+}
+
+impl Verifier<'_> {
+    fn check_entries(&mut self, entries: &[SpecEntry]) -> io::Result<()> {
+        // Entries inside a missing directory are missing too, only the directory is reported.
+        let mut missing_dirs: Vec<&Path> = Vec::new();
+
+        for entry in entries {
+            self.expected_paths.insert(entry.path.clone());
+            if entry.is_ignored {
+                self.ignored_paths.insert(entry.path.clone());
+            }
+
+            if missing_dirs.iter().any(|dir| entry.path.starts_with(dir)) {
+                continue;
+            }
+
+            self.drift.checked += 1;
+
+            let stat = match EntryStat::read(&self.root_path.join(&entry.path)) {
+                Ok(stat) => stat,
+                Err(_) => {
+                    if entry.is_dir() {
+                        missing_dirs.push(&entry.path);
+                    }
+                    if !entry.is_optional {
+                        self.drift.missing += 1;
+                        writeln!(self.writer, "missing: {}", entry.get_display_path())?;
+                    }
+                    continue;
+                }
+            };
+
+            let mut is_changed = false;
+
+            for (keyword, expected) in &entry.values {
+                let Some(found) =
+                    find_mismatch(*keyword, expected, &stat, &mut self.drift.hash_stats)
+                else {
+                    continue;
+                };
+
+                is_changed = true;
+                writeln!(
+                    self.writer,
+                    "{}: {} expected {expected} found {found}",
+                    entry.get_display_path(),
+                    keyword.get_name()
+                )?;
+
+                // Nothing else is comparable once the type is different.
+                if *keyword == SpecKeyword::Type {
+                    break;
+                }
+            }
+
+            if is_changed {
+                self.drift.changed += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Walks the directory for entries the spec doesn't mention, without going into them.
+    ///
+    /// A directory that can't be read is reported and counted in the errors, since extra
+    /// entries could be hiding in it.
+    fn find_extra_entries(&mut self, path: &Path) -> io::Result<()> {
+        let read_dir = match fs::read_dir(path) {
+            Ok(read_dir) => read_dir,
+            Err(error) => return self.write_error(path, &error),
+        };
+
+        let mut entries: Vec<fs::DirEntry> = Vec::new();
+        for entry in read_dir {
+            match entry {
+                Ok(entry) => entries.push(entry),
+                Err(error) => self.write_error(path, &error)?,
+            }
+        }
+        entries.sort_by_key(|entry| entry.file_name());
+
+        self.entry_filter.enter_directory(path);
+
+        for entry in entries {
+            let entry_path = entry.path();
+            let is_dir = entry.file_type().is_ok_and(|file_type| file_type.is_dir());
+
+            if !self.entry_filter.is_visible(&entry_path, is_dir) {
+                continue;
+            }
+
+            let relative_path = entry_path
+                .strip_prefix(&self.root_path)
+                .unwrap_or(&entry_path)
+                .to_path_buf();
+
+            if !self.expected_paths.contains(&relative_path) {
+                self.drift.extra += 1;
+                writeln!(self.writer, "extra: {}", get_display_path(&relative_path))?;
+                continue;
+            }
+
+            if is_dir && !self.ignored_paths.contains(&relative_path) {
+                self.find_extra_entries(&entry_path)?;
+            }
+        }
+
+        self.entry_filter.leave_directory();
+
+        Ok(())
+    }
+
+    fn write_error(&mut self, path: &Path, error: &io::Error) -> io::Result<()> {
+        let error = WalkError::from_io_error(error);
+        self.drift.errors.add(error);
+
+        let relative_path = path.strip_prefix(&self.root_path).unwrap_or(path);
+        writeln!(
+            self.writer,
+            "unreadable: {} ({})",
+            get_display_path(relative_path),
+            error.get_message()
+        )
+    }
+}
+
+/// Compares one keyword of the spec with the entry on disk, giving what was found instead
+/// when they differ. Numbers are compared by value, so `mode=755` matches `mode=0755`.
+fn find_mismatch(
+    keyword: SpecKeyword,
+    expected: &str,
+    stat: &EntryStat,
+    hash_stats: &mut HashStats,
+) -> Option<String> {
+    let found = match keyword {
+        SpecKeyword::Type => stat.spec_type.get_name().to_string(),
+        SpecKeyword::Uid => stat.uid?.to_string(),
+        SpecKeyword::Gid => stat.gid?.to_string(),
+        SpecKeyword::Nlink => stat.nlink?.to_string(),
+        SpecKeyword::Size => stat.size.to_string(),
+        SpecKeyword::Mode => {
+            let mode = stat.mode?;
+            return match u32::from_str_radix(expected, 8) {
+                Ok(expected) if expected == mode => None,
+                _ => Some(format_mode(mode)),
+            };
+        }
+        SpecKeyword::Link => {
+            let link = stat
+                .link
+                .as_ref()
+                .map(|link| link.as_os_str().as_encoded_bytes());
+            return match link {
+                Some(link) if link == decode_name_bytes(expected) => None,
+                Some(link) => Some(encode_name(link)),
+                None => Some("no link".to_string()),
+            };
+        }
+        SpecKeyword::Time => {
+            let (seconds, nanos) = stat.time?;
+            return match parse_time(expected) {
+                Some(expected) if expected == (seconds, nanos) => None,
+                _ => Some(format!("{seconds}.{nanos:09}")),
+            };
+        }
+        SpecKeyword::Md5Digest | SpecKeyword::Sha256Digest | SpecKeyword::Blake3Digest => {
+            let hash_algorithm = keyword.get_hash_algorithm()?;
+            return match stat.get_digest(hash_algorithm, hash_stats) {
+                Some(Ok(digest)) if digest.eq_ignore_ascii_case(expected) => None,
+                Some(Ok(digest)) => Some(digest),
+                Some(Err(error)) => Some(format!("an unreadable file ({error})")),
+                None => Some(format!("a {}", stat.spec_type.get_name())),
+            };
+        }
+    };
+
+    match (expected.parse::<u64>(), found.parse::<u64>()) {
+        (Ok(expected), Ok(found_number)) if expected == found_number => None,
+        _ if expected == found => None,
+        _ => Some(found),
+    }
+}
+
+/// `1700000000.000000123`, the part after the dot being a fraction of a second with up to
+/// nine digits, so `.5` is half a second.
+fn parse_time(time: &str) -> Option<(u64, u32)> {
+    let (seconds, fraction) = time.split_once('.').unwrap_or((time, "0"));
+    if fraction.is_empty()
+        || fraction.len() > 9
+        || !fraction.bytes().all(|byte| byte.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let nanos: u32 = format!("{fraction:0<9}").parse().ok()?;
+    Some((seconds.parse().ok()?, nanos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::SpecType;
+    use std::env;
+    use std::process;
+
+    fn get_stat() -> EntryStat {
+        EntryStat {
+            path: PathBuf::from("main.rs"),
+            spec_type: SpecType::File,
+            uid: Some(0),
+            gid: Some(0),
+            mode: Some(0o644),
+            nlink: Some(1),
+            size: 12,
+            link: None,
+            time: Some((1700000000, 5)),
+        }
+    }
+
+    #[test]
+    fn test_find_mismatch() {
+        let stat = get_stat();
+        assert_eq!(
+            find_mismatch(SpecKeyword::Size, "12", &stat, &mut HashStats::default()),
+            None
+        );
+        assert_eq!(
+            find_mismatch(SpecKeyword::Size, "10", &stat, &mut HashStats::default()),
+            Some("12".to_string())
+        );
+        assert_eq!(
+            find_mismatch(SpecKeyword::Mode, "644", &stat, &mut HashStats::default()),
+            None
+        );
+        assert_eq!(
+            find_mismatch(SpecKeyword::Mode, "0755", &stat, &mut HashStats::default()),
+            Some("0644".to_string())
+        );
+        assert_eq!(
+            find_mismatch(SpecKeyword::Type, "dir", &stat, &mut HashStats::default()),
+            Some("file".to_string())
+        );
+        assert_eq!(
+            find_mismatch(
+                SpecKeyword::Time,
+                "1700000000.000000005",
+                &stat,
+                &mut HashStats::default()
+            ),
+            None
+        );
+        assert_eq!(
+            find_mismatch(SpecKeyword::Link, "a.txt", &stat, &mut HashStats::default()),
+            Some("no link".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("1700000000.000000123"), Some((1700000000, 123)));
+        assert_eq!(parse_time("1700000000"), Some((1700000000, 0)));
+        assert_eq!(parse_time("1700000000.5"), Some((1700000000, 500_000_000)));
+        assert_eq!(parse_time("1700000000.0000000001"), None);
+        assert_eq!(parse_time("1700000000.+5"), None);
+        assert_eq!(parse_time("soon"), None);
+    }
+
+    #[test]
+    fn test_verify_directory_with_ignore_file() {
+        let root_path = env::temp_dir().join(format!("mtree-verify-test-{}", process::id()));
+        fs::create_dir_all(&root_path).unwrap();
+        fs::write(root_path.join("a.txt"), "a").unwrap();
+        fs::write(root_path.join("evil.sh"), "").unwrap();
+        fs::write(root_path.join(".mtreeignore"), "evil.sh\n").unwrap();
+        let spec_path = env::temp_dir().join(format!("mtree-verify-spec-{}", process::id()));
+        fs::write(&spec_path, ". type=dir\n    a.txt type=file size=1\n..\n").unwrap();
+
+        let args: Vec<String> = ["mtree", "verify", "-f"]
+            .into_iter()
+            .map(String::from)
+            .chain([spec_path.display().to_string()])
+            .chain([root_path.display().to_string()])
+            .collect();
+        let mut output: Vec<u8> = Vec::new();
+        let drift = verify_directory(&Config::from(args), &mut output);
+        fs::remove_dir_all(&root_path).unwrap();
+        fs::remove_file(&spec_path).unwrap();
+
+        // The ignore file of the tree hides nothing, it is reported like any other entry.
+        assert_eq!(drift.map(|drift| drift.extra), Ok(2));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "extra: ./.mtreeignore\nextra: ./evil.sh\n"
+        );
+    }
+}
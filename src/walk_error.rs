@@ -0,0 +1,151 @@
+//! What can go wrong while walking a directory. None of it stops the walk, the entry is
+//! marked in the tree, counted for the `-v` report and decides the exit code.
+use super::utils::format_count_of;
+use std::io;
+
+/// Why a directory or entry couldn't be read.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum WalkError {
+    PermissionDenied,
+    /// It was removed or renamed while the walk was going on.
+    NotFound,
+    /// It was replaced by something other than a directory while the walk was going on.
+    NotADirectory,
+    /// Anything else, with the error number of the system when there is one.
+    Io(Option<i32>),
+}
+
+impl WalkError {
+    pub fn from_io_error(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied,
+            io::ErrorKind::NotFound => Self::NotFound,
+            io::ErrorKind::NotADirectory => Self::NotADirectory,
+            _ => Self::Io(error.raw_os_error()),
+        }
+    }
+
+    /// `permission denied`, written inline between brackets in the tree.
+    pub fn get_message(&self) -> String {
+        match self {
+            Self::PermissionDenied => "permission denied".to_string(),
+            Self::NotFound => "not found".to_string(),
+            Self::NotADirectory => "not a directory".to_string(),
+            Self::Io(Some(errno)) => format!("I/O error, errno {errno}"),
+            Self::Io(None) => "I/O error".to_string(),
+        }
+    }
+
+    /// `permission_denied`, for the JSON outputs.
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::PermissionDenied => "permission_denied",
+            Self::NotFound => "not_found",
+            Self::NotADirectory => "not_a_directory",
+            Self::Io(_) => "io",
+        }
+    }
+}
+
+/// How many of each error the walk ran into.
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct ErrorTally {
+    pub permission_denied: usize,
+    pub not_found: usize,
+    pub not_a_directory: usize,
+    pub io: usize,
+}
+
+impl ErrorTally {
+    pub fn add(&mut self, error: WalkError) {
+        match error {
+            WalkError::PermissionDenied => self.permission_denied += 1,
+            WalkError::NotFound => self.not_found += 1,
+            WalkError::NotADirectory => self.not_a_directory += 1,
+            WalkError::Io(_) => self.io += 1,
+        }
+    }
+
+    /// Counts the errors of another walk too.
+    pub fn add_tally(&mut self, other: &Self) {
+        self.permission_denied += other.permission_denied;
+        self.not_found += other.not_found;
+        self.not_a_directory += other.not_a_directory;
+        self.io += other.io;
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.permission_denied + self.not_found + self.not_a_directory + self.io
+    }
+
+    /// The most serious kind of error that happened: anything unexpected, then entries that
+    /// changed during the walk, then missing permissions.
+    pub fn get_worst(&self) -> Option<WalkError> {
+        if self.io > 0 {
+            Some(WalkError::Io(None))
+        } else if self.not_a_directory > 0 {
+            Some(WalkError::NotADirectory)
+        } else if self.not_found > 0 {
+            Some(WalkError::NotFound)
+        } else if self.permission_denied > 0 {
+            Some(WalkError::PermissionDenied)
+        } else {
+            None
+        }
+    }
+
+    /// `3 errors: 2 permission denied, 1 not found`.
+    pub fn get_message(&self) -> String {
+        let counts: Vec<String> = [
+            (self.permission_denied, WalkError::PermissionDenied),
+            (self.not_found, WalkError::NotFound),
+            (self.not_a_directory, WalkError::NotADirectory),
+            (self.io, WalkError::Io(None)),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, error)| format!("{count} {}", error.get_message()))
+        .collect();
+
+        format!(
+            "{}: {}",
+            format_count_of(self.get_count(), "error", "errors"),
+            counts.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_walk_error_from_io_error() {
+        let error = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert_eq!(
+            WalkError::from_io_error(&error),
+            WalkError::PermissionDenied
+        );
+
+        let error = io::Error::other("the disk is on fire");
+        assert_eq!(WalkError::from_io_error(&error), WalkError::Io(None));
+        assert_eq!(WalkError::Io(Some(5)).get_message(), "I/O error, errno 5");
+    }
+
+    #[test]
+    fn test_error_tally() {
+        let mut errors = ErrorTally::default();
+        assert_eq!(errors.get_worst(), None);
+
+        errors.add(WalkError::PermissionDenied);
+        errors.add(WalkError::PermissionDenied);
+        assert_eq!(errors.get_worst(), Some(WalkError::PermissionDenied));
+
+        errors.add(WalkError::NotFound);
+        assert_eq!(errors.get_worst(), Some(WalkError::NotFound));
+        assert_eq!(
+            errors.get_message(),
+            "3 errors: 2 permission denied, 1 not found"
+        );
+    }
+}